@@ -0,0 +1,115 @@
+extern crate graph_match;
+
+use std::time::{Duration, Instant};
+use graph_match::graph::Graph;
+use graph_match::matching::{Attributes, Component, EqualityRequirement, MatchedComponents};
+use graph_match::prepared::PreparedQuery;
+
+// Times the prepared matcher, on a graph and on its frozen copy, against the
+// matcher it replaced. Half the heads in the graph have too few edges to
+// match.
+//
+//     cargo bench --bench matching
+
+const HEADS: usize = 200_000;
+const RUNS: u32 = 5;
+
+fn attributes(pairs: &[(&str, &str)]) -> Attributes {
+    Some(pairs.iter().map(|&(key, value)| (key.to_string(), value.to_string())).collect())
+}
+
+// verbs, half of them with a single modifier and the other half with six
+// modifiers ahead of their subject and object
+fn graph() -> Graph {
+    let mut graph = Graph::new();
+    for head in 0..HEADS {
+        let verb = graph.add_node(format!("verb{}", head), attributes(&[("pos", "VB")]));
+        let relations: &[&str] = if head % 2 == 0 {
+            &["amod"]
+        } else {
+            &["amod", "det", "advmod", "amod", "det", "advmod", "nsubj", "dobj"]
+        };
+        for (dependent, &relation) in relations.iter().enumerate() {
+            let noun = graph.add_node(format!("noun{}_{}", head, dependent), attributes(&[("pos", "NN")]));
+            graph.add_edge(verb, noun, relation, attributes(&[("relation", relation)]));
+        }
+    }
+    graph
+}
+
+fn query() -> Graph {
+    let mut query = Graph::new();
+    let verb = query.add_node("verb", attributes(&[("pos", "VB")]));
+    for &relation in &["nsubj", "dobj"] {
+        let noun = query.add_node(relation, attributes(&[("pos", "NN")]));
+        query.add_edge(verb, noun, relation, attributes(&[("relation", relation)]));
+    }
+    query
+}
+
+// recusive_node_match and match_graph as they were before PreparedQuery: roots
+// are found by matching every node, and each query edge takes the first
+// matching edge out of a node listed afresh by edges_for_node.
+fn baseline_match(query_index: usize, graph_index: usize, query: &Graph, graph: &Graph, source_edge: Option<usize>,
+                  equality: &EqualityRequirement) -> MatchedComponents {
+    if !query.nodes[query_index].matches(&graph.nodes[graph_index], equality) {
+        return MatchedComponents { list: vec![] };
+    }
+    let mut matched_components = MatchedComponents { list: vec![Component { from_edge: source_edge, node: graph_index }] };
+    for query_edge_index in query.edges_for_node(query_index) {
+        let mut matching_edge_in_graph = None;
+        for graph_edge_index in graph.edges_for_node(graph_index) {
+            if query.edges[query_edge_index].matches(&graph.edges[graph_edge_index], equality) {
+                matching_edge_in_graph = Some(graph_edge_index);
+                break;
+            }
+        }
+        match matching_edge_in_graph {
+            Some(edge) => {
+                let query_target = query.edges[query_edge_index].target;
+                let target = graph.edges[edge].target;
+                if !query.nodes[query_target].matches(&graph.nodes[target], equality) {
+                    return MatchedComponents { list: vec![] };
+                }
+                let descendents = baseline_match(query_target, target, query, graph, Some(edge), equality);
+                matched_components.list.extend(descendents.list);
+            }
+            None => return MatchedComponents { list: vec![] },
+        }
+    }
+    matched_components
+}
+
+fn baseline(query: &Graph, graph: &Graph, equality: &EqualityRequirement) -> Vec<MatchedComponents> {
+    let roots: Vec<usize> = (0..graph.nodes.len()).filter(|&index| query.nodes[0].matches(&graph.nodes[index], equality)).collect();
+    roots.iter()
+        .map(|&root| baseline_match(0, root, query, graph, None, equality))
+        .filter(|matched_components| matched_components.list.len() == query.nodes.len())
+        .collect()
+}
+
+fn time<T, F: FnMut() -> T>(mut run: F) -> (Duration, T) {
+    let mut result = run();
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        result = run();
+    }
+    (start.elapsed() / RUNS, result)
+}
+
+fn main() {
+    let equality = EqualityRequirement::Complete;
+    let query = query();
+    let frozen = graph().freeze();
+    let graph = graph();
+    let prepared = PreparedQuery::new(&query, 0, &equality);
+
+    let (baseline, expected) = time(|| baseline(&query, &graph, &equality));
+    let (graph_prepared, matches) = time(|| prepared.execute(&graph, None));
+    assert_eq!(expected, matches);
+    let (frozen_prepared, matches) = time(|| prepared.execute(&frozen, None));
+    assert_eq!(expected, matches);
+
+    println!("{} matches", matches.len());
+    println!("baseline {:?}, prepared on graph {:?}, prepared on frozen graph {:?}", baseline, graph_prepared, frozen_prepared);
+}
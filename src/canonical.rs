@@ -0,0 +1,436 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use graph;
+use node;
+use edge;
+use matching::AttributePairs;
+
+// A node or edge's identifier and sorted attribute pairs.
+type Label = (String, Vec<(String, String)>);
+
+// A node's outgoing and incoming edges as sorted (edge label rank, node) pairs.
+type Neighbours = (Vec<(usize, usize)>, Vec<(usize, usize)>);
+
+// A graph written out with its nodes in a canonical order: two graphs have the
+// same canonical form exactly when they are isomorphic, a node's identifier
+// and attributes having to match as well as its edges'. The order of each
+// node's outgoing edges and removed nodes and edges do not count.
+//
+// Forms can be compared, hashed and used as map keys to deduplicate graphs.
+#[derive(Clone,Debug,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub struct CanonicalForm {
+    nodes: Vec<Label>,
+    // (source, target, label) with nodes given by their canonical position
+    edges: Vec<(usize, usize, Label)>,
+}
+
+impl CanonicalForm {
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+}
+
+pub fn canonical_form<N, E>(graph: &graph::Graph<N, E>) -> CanonicalForm
+    where N: AttributePairs, E: AttributePairs {
+        Canonizer::new(graph).run().0
+    }
+
+// The graph's nodes in canonical order. Isomorphic graphs list corresponding
+// nodes at the same positions, although which of several interchangeable nodes
+// comes first is arbitrary.
+pub fn canonical_order<N, E>(graph: &graph::Graph<N, E>) -> Vec<node::Index>
+    where N: AttributePairs, E: AttributePairs {
+        Canonizer::new(graph).run().1
+    }
+
+// A hash of the canonical form, equal for isomorphic graphs. It comes from
+// the standard library's default hasher, so it is only comparable within one
+// build of a program and should not be stored.
+pub fn canonical_hash<N, E>(graph: &graph::Graph<N, E>) -> u64
+    where N: AttributePairs, E: AttributePairs {
+        let mut hasher = DefaultHasher::new();
+        canonical_form(graph).hash(&mut hasher);
+        hasher.finish()
+    }
+
+pub fn is_isomorphic<N, E>(graph1: &graph::Graph<N, E>, graph2: &graph::Graph<N, E>) -> bool
+    where N: AttributePairs, E: AttributePairs {
+        graph1.node_count() == graph2.node_count() && graph1.edge_count() == graph2.edge_count() &&
+            canonical_form(graph1) == canonical_form(graph2)
+    }
+
+fn label(identifier: &str, mut pairs: Vec<(String, String)>) -> Label {
+    pairs.sort();
+    (identifier.to_string(), pairs)
+}
+
+// Ranks the distinct values in order, so equal values get equal ranks and the
+// ranks depend only on the values, never on node indexes.
+fn ranks<T: Ord + Clone>(values: &[T]) -> Vec<usize> {
+    let mut sorted = values.to_vec();
+    sorted.sort();
+    sorted.dedup();
+    values.iter().map(|value| sorted.binary_search(value).expect("value was sorted in")).collect()
+}
+
+// Forests, where every node has at most one incoming edge and there are no
+// cycles, are ordered directly by their subtrees as in Aho, Hopcroft and
+// Ullman's tree isomorphism test, see forest_colours. Other graphs go through
+// colour refinement with individualization: nodes are coloured by their
+// labels, colours are refined by the colours and edge labels of neighbours
+// until stable, and while some nodes still share a colour each of them in turn
+// is given a colour of its own, keeping the smallest form reached. Refinement
+// alone takes polynomial time but the individualization search can grow
+// factorially on graphs with many symmetric nodes that are not forests.
+struct Canonizer {
+    // live nodes, positions below are into this list
+    nodes: Vec<node::Index>,
+    node_labels: Vec<Label>,
+    // (source, target, label rank) between positions
+    edges: Vec<(usize, usize, usize)>,
+    edge_labels: Vec<Label>,
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+}
+
+impl Canonizer {
+    fn new<N, E>(graph: &graph::Graph<N, E>) -> Canonizer
+        where N: AttributePairs, E: AttributePairs {
+            let nodes = graph.node_indices();
+            let mut positions = vec![0; graph.nodes.len()];
+            for (position, &node_index) in nodes.iter().enumerate() {
+                positions[node_index] = position;
+            }
+            let node_labels: Vec<Label> = nodes.iter()
+                .map(|&node_index| label(&graph.nodes[node_index].identifier, graph.nodes[node_index].attributes.attribute_pairs()))
+                .collect();
+            let live_edges: Vec<edge::Index> = (0..graph.edges.len()).filter(|&index| !graph.edges[index].removed).collect();
+            let edge_labels: Vec<Label> = live_edges.iter()
+                .map(|&edge_index| label(&graph.edges[edge_index].identifier, graph.edges[edge_index].attributes.attribute_pairs()))
+                .collect();
+            let label_ranks = ranks(&edge_labels);
+            let mut outgoing = vec![vec![]; nodes.len()];
+            let mut incoming = vec![vec![]; nodes.len()];
+            let mut edges = vec![];
+            for (position, &edge_index) in live_edges.iter().enumerate() {
+                let (source, target) = (positions[graph.edges[edge_index].source], positions[graph.edges[edge_index].target]);
+                outgoing[source].push(position);
+                incoming[target].push(position);
+                edges.push((source, target, label_ranks[position]));
+            }
+            Canonizer { nodes, node_labels, edges, edge_labels, outgoing, incoming }
+        }
+
+    fn run(&self) -> (CanonicalForm, Vec<node::Index>) {
+        let (form, order) = match self.forest_colours() {
+            Some(colours) => self.form(&colours),
+            None => self.search(self.refine(ranks(&self.node_labels))),
+        };
+        (form, order.into_iter().map(|position| self.nodes[position]).collect())
+    }
+
+    // A forest's nodes listed depth first from its roots, each node's children
+    // in the order of their edge labels and subtree codes, given as the
+    // position of each node in that list. Subtrees get the same code exactly
+    // when they are isomorphic, so which of several equal subtrees comes first
+    // does not change the form. None when the graph is not a forest.
+    fn forest_colours(&self) -> Option<Vec<usize>> {
+        if self.incoming.iter().any(|edges| edges.len() > 1) {
+            return None;
+        }
+        let roots: Vec<usize> = (0..self.nodes.len()).filter(|&position| self.incoming[position].is_empty()).collect();
+        // every node is reached from a root unless there is a cycle
+        let mut reached = Vec::with_capacity(self.nodes.len());
+        let mut stack = roots.clone();
+        while let Some(position) = stack.pop() {
+            reached.push(position);
+            stack.extend(self.outgoing[position].iter().map(|&edge| self.edges[edge].1));
+        }
+        if reached.len() < self.nodes.len() {
+            return None;
+        }
+
+        // codes are given height by height, a subtree's code ranking its label
+        // and its children's edge labels and codes among the subtrees of the
+        // same height
+        let mut heights = vec![0; self.nodes.len()];
+        for &position in reached.iter().rev() {
+            heights[position] = self.outgoing[position].iter()
+                .map(|&edge| heights[self.edges[edge].1] + 1)
+                .max()
+                .unwrap_or(0);
+        }
+        let labels = ranks(&self.node_labels);
+        let mut by_height = vec![vec![]; heights.iter().max().map_or(0, |&height| height + 1)];
+        for position in 0..self.nodes.len() {
+            by_height[heights[position]].push(position);
+        }
+        let mut codes = vec![0; self.nodes.len()];
+        let mut next_code = 0;
+        for positions in by_height {
+            let signatures: Vec<(usize, Vec<(usize, usize)>)> = positions.iter()
+                .map(|&position| (labels[position], self.children(position, &codes)))
+                .collect();
+            let signature_ranks = ranks(&signatures);
+            for (&position, &rank) in positions.iter().zip(&signature_ranks) {
+                codes[position] = next_code + rank;
+            }
+            next_code += count_classes(&signature_ranks);
+        }
+
+        let mut stack = roots;
+        stack.sort_by_key(|&position| Reverse(codes[position]));
+        let mut colours = vec![0; self.nodes.len()];
+        let mut next_colour = 0;
+        while let Some(position) = stack.pop() {
+            colours[position] = next_colour;
+            next_colour += 1;
+            let mut children: Vec<(usize, usize, usize)> = self.outgoing[position].iter()
+                .map(|&edge| (self.edges[edge].2, codes[self.edges[edge].1], self.edges[edge].1))
+                .collect();
+            children.sort();
+            stack.extend(children.into_iter().rev().map(|(_, _, child)| child));
+        }
+        Some(colours)
+    }
+
+    // a node's outgoing edges as sorted (edge label rank, target code) pairs
+    fn children(&self, position: usize, codes: &[usize]) -> Vec<(usize, usize)> {
+        let mut children: Vec<(usize, usize)> = self.outgoing[position].iter()
+            .map(|&edge| (self.edges[edge].2, codes[self.edges[edge].1]))
+            .collect();
+        children.sort();
+        children
+    }
+
+    fn refine(&self, mut colours: Vec<usize>) -> Vec<usize> {
+        let mut classes = count_classes(&colours);
+        loop {
+            let signatures: Vec<(usize, Neighbours)> = (0..colours.len())
+                .map(|position| (colours[position], self.neighbours(position, |other| colours[other])))
+                .collect();
+            colours = ranks(&signatures);
+            let refined = count_classes(&colours);
+            if refined == classes {
+                return colours;
+            }
+            classes = refined;
+        }
+    }
+
+    fn search(&self, colours: Vec<usize>) -> (CanonicalForm, Vec<usize>) {
+        // the smallest colour shared by several nodes
+        let mut sizes = vec![0; colours.len()];
+        for &colour in &colours {
+            sizes[colour] += 1;
+        }
+        let shared = match (0..sizes.len()).find(|&colour| sizes[colour] > 1) {
+            Some(colour) => colour,
+            None => return self.form(&colours),
+        };
+
+        let mut best: Option<(CanonicalForm, Vec<usize>)> = None;
+        let mut tried: Vec<Neighbours> = vec![];
+        for position in (0..colours.len()).filter(|&position| colours[position] == shared) {
+            // nodes with the same edges to the same nodes can be swapped,
+            // so only one of them needs trying
+            let neighbours = self.neighbours(position, |other| other);
+            if tried.contains(&neighbours) {
+                continue;
+            }
+            tried.push(neighbours);
+
+            let individualized: Vec<usize> = colours.iter().enumerate()
+                .map(|(other, &colour)| if other == position { 2 * colour } else { 2 * colour + 1 })
+                .collect();
+            let candidate = self.search(self.refine(ranks(&individualized)));
+            let better = match best {
+                Some((ref best_form, _)) => candidate.0 < *best_form,
+                None => true,
+            };
+            if better {
+                best = Some(candidate);
+            }
+        }
+        best.expect("a shared colour has members")
+    }
+
+    // neighbouring nodes are given by key, their colour or their position
+    fn neighbours<F: Fn(usize) -> usize>(&self, position: usize, key: F) -> Neighbours {
+        let mut outgoing: Vec<(usize, usize)> = self.outgoing[position].iter()
+            .map(|&edge| (self.edges[edge].2, key(self.edges[edge].1)))
+            .collect();
+        let mut incoming: Vec<(usize, usize)> = self.incoming[position].iter()
+            .map(|&edge| (self.edges[edge].2, key(self.edges[edge].0)))
+            .collect();
+        outgoing.sort();
+        incoming.sort();
+        (outgoing, incoming)
+    }
+
+    // with every node of its own colour, colours are canonical positions
+    fn form(&self, colours: &[usize]) -> (CanonicalForm, Vec<usize>) {
+        let mut order = vec![0; colours.len()];
+        for (position, &colour) in colours.iter().enumerate() {
+            order[colour] = position;
+        }
+        let nodes = order.iter().map(|&position| self.node_labels[position].clone()).collect();
+        let mut edges: Vec<(usize, usize, Label)> = self.edges.iter().enumerate()
+            .map(|(edge, &(source, target, _))| (colours[source], colours[target], self.edge_labels[edge].clone()))
+            .collect();
+        edges.sort();
+        (CanonicalForm { nodes, edges }, order)
+    }
+}
+
+// colours are ranks, so the classes are numbered from 0
+fn count_classes(colours: &[usize]) -> usize {
+    colours.iter().max().map_or(0, |&colour| colour + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use graph::Graph;
+    use matching::Attributes;
+    use super::*;
+
+    fn word(word: &str) -> Attributes {
+        let mut attributes = HashMap::new();
+        attributes.insert("word".to_string(), word.to_string());
+        Some(attributes)
+    }
+
+    // "see" with its subject and object, words added in the given order
+    fn clause(order: &[&str]) -> Graph {
+        let mut graph = Graph::new();
+        for &text in order {
+            graph.add_node("token", word(text));
+        }
+        let position = |text: &str| order.iter().position(|&other| other == text).unwrap();
+        graph.add_edge(position("see"), position("I"), "nsubj", None);
+        graph.add_edge(position("see"), position("dog"), "dobj", None);
+        graph
+    }
+
+    #[test]
+    fn isomorphic_graphs() {
+        let graph1 = clause(&["see", "I", "dog"]);
+        let graph2 = clause(&["dog", "see", "I"]);
+        assert!(is_isomorphic(&graph1, &graph2));
+        assert_eq!(canonical_form(&graph1), canonical_form(&graph2));
+        assert_eq!(canonical_hash(&graph1), canonical_hash(&graph2));
+        let words = |graph: &Graph| -> Vec<String> {
+            canonical_order(graph).into_iter()
+                .map(|node_index| graph.nodes[node_index].attributes.as_ref().unwrap()["word"].clone())
+                .collect()
+        };
+        assert_eq!(words(&graph1), words(&graph2));
+
+        let mut graph3 = clause(&["see", "I", "dog"]);
+        graph3.set_edge_identifier(0, "dobj");
+        graph3.set_edge_identifier(1, "nsubj");
+        assert!(!is_isomorphic(&graph1, &graph3));
+        graph3.set_node_attribute(1, "word", "dog");
+        graph3.set_node_attribute(2, "word", "I");
+        assert!(is_isomorphic(&graph1, &graph3));
+    }
+
+    #[test]
+    fn symmetric_graphs() {
+        // a cycle and a star of indistinguishable nodes need individualizing
+        let cycle = |order: &[usize]| {
+            let mut graph = Graph::new();
+            for _ in 0..6 {
+                graph.add_node("node", None);
+            }
+            for n in 0..6 {
+                graph.add_edge(order[n], order[(n + 1) % 6], "edge", None);
+            }
+            graph
+        };
+        assert!(is_isomorphic(&cycle(&[0, 1, 2, 3, 4, 5]), &cycle(&[3, 5, 0, 2, 1, 4])));
+
+        // two triangles have as many nodes and edges as a hexagon
+        let mut triangles = Graph::new();
+        for _ in 0..6 {
+            triangles.add_node("node", None);
+        }
+        for &(source, target) in &[(0, 1), (1, 2), (2, 0), (3, 4), (4, 5), (5, 3)] {
+            triangles.add_edge(source, target, "edge", None);
+        }
+        assert!(!is_isomorphic(&cycle(&[0, 1, 2, 3, 4, 5]), &triangles));
+
+        // a node with two incoming edges makes a graph no forest
+        let diamond = |order: &[usize]| {
+            let mut graph = Graph::new();
+            for _ in 0..4 {
+                graph.add_node("node", None);
+            }
+            for &(source, target) in &[(0, 1), (0, 2), (1, 3), (2, 3)] {
+                graph.add_edge(order[source], order[target], "edge", None);
+            }
+            graph
+        };
+        assert!(is_isomorphic(&diamond(&[0, 1, 2, 3]), &diamond(&[3, 2, 0, 1])));
+
+        let mut star = Graph::new();
+        star.add_node("root", None);
+        for _ in 0..30 {
+            let leaf = star.add_node("leaf", None);
+            star.add_edge(0, leaf, "edge", None);
+        }
+        assert_eq!(31, canonical_form(&star).node_count());
+    }
+
+    #[test]
+    fn symmetric_trees() {
+        // thirty unlabelled amod and det chains under one root, added in two
+        // orders, left the search trying every order of the chains
+        let chains = |reversed: bool| {
+            let mut graph = Graph::new();
+            let root = graph.add_node("node", None);
+            let mut modifiers = vec![];
+            for _ in 0..30 {
+                modifiers.push(graph.add_node("node", None));
+            }
+            if reversed {
+                modifiers.reverse();
+            }
+            for &modifier in &modifiers {
+                let determiner = graph.add_node("node", None);
+                graph.add_edge(modifier, determiner, "det", None);
+                graph.add_edge(root, modifier, "amod", None);
+            }
+            graph
+        };
+        assert!(is_isomorphic(&chains(false), &chains(true)));
+        assert_eq!(61, canonical_form(&chains(false)).node_count());
+
+        let mut relabelled = chains(true);
+        relabelled.set_edge_identifier(0, "amod");
+        assert!(!is_isomorphic(&chains(false), &relabelled));
+    }
+
+    #[test]
+    fn removed_entries_and_deduplication() {
+        let mut graph = clause(&["see", "I", "dog"]);
+        let extra = graph.add_node("token", word("cat"));
+        graph.add_edge(0, extra, "dobj", None);
+        graph.remove_node(extra);
+        assert!(is_isomorphic(&graph, &clause(&["I", "dog", "see"])));
+
+        let mut seen = HashMap::new();
+        for order in &[["see", "I", "dog"], ["I", "see", "dog"], ["dog", "I", "see"]] {
+            *seen.entry(canonical_form(&clause(order))).or_insert(0) += 1;
+        }
+        seen.entry(canonical_form(&Graph::new())).or_insert(0);
+        assert_eq!(2, seen.len());
+        assert_eq!(0, canonical_form(&Graph::new()).edge_count());
+    }
+}
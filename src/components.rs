@@ -0,0 +1,138 @@
+use graph;
+use node;
+use edge;
+use subgraph;
+
+fn find(parents: &mut [usize], mut node_index: usize) -> usize {
+    while parents[node_index] != node_index {
+        parents[node_index] = parents[parents[node_index]];
+        node_index = parents[node_index];
+    }
+    node_index
+}
+
+// Labels each node with the weakly connected component it belongs to, that is
+// the nodes it can reach by following edges in either direction. Components
+// are numbered in order of their lowest node index, removed nodes have no label.
+pub fn component_labels<N, E>(graph: &graph::Graph<N, E>) -> Vec<Option<usize>> {
+    let mut parents: Vec<usize> = (0..graph.nodes.len()).collect();
+    for edge in &graph.edges {
+        if edge.removed {
+            continue;
+        }
+        let source = find(&mut parents, edge.source);
+        let target = find(&mut parents, edge.target);
+        if source != target {
+            // keep the lower index as the representative
+            if source < target {
+                parents[target] = source;
+            } else {
+                parents[source] = target;
+            }
+        }
+    }
+
+    let mut labels = vec![None; graph.nodes.len()];
+    let mut count = 0;
+    for node_index in graph.node_indices() {
+        let representative = find(&mut parents, node_index);
+        if representative == node_index {
+            labels[node_index] = Some(count);
+            count += 1;
+        } else {
+            labels[node_index] = labels[representative];
+        }
+    }
+    labels
+}
+
+pub fn weakly_connected_components<N, E>(graph: &graph::Graph<N, E>) -> Vec<Vec<node::Index>> {
+    group(&component_labels(graph))
+}
+
+fn group(labels: &[Option<usize>]) -> Vec<Vec<node::Index>> {
+    let mut components: Vec<Vec<node::Index>> = vec![];
+    for (node_index, label) in labels.iter().enumerate() {
+        if let Some(label) = *label {
+            if label == components.len() {
+                components.push(vec![]);
+            }
+            components[label].push(node_index);
+        }
+    }
+    components
+}
+
+// Splits a graph into one standalone graph per weakly connected component, for
+// example to recover the sentences of a document loaded as a single graph.
+// Each subgraph maps its nodes and edges back to the original graph.
+pub fn split<N: Clone, E: Clone>(graph: &graph::Graph<N, E>) -> Vec<subgraph::Subgraph<N, E>> {
+    let labels = component_labels(graph);
+    let node_lists = group(&labels);
+    let mut edge_lists: Vec<Vec<edge::Index>> = vec![vec![]; node_lists.len()];
+    for (edge_index, edge) in graph.edges.iter().enumerate() {
+        if !edge.removed {
+            let label = labels[edge.source].expect("live edges join live nodes");
+            edge_lists[label].push(edge_index);
+        }
+    }
+
+    node_lists.iter().zip(edge_lists.iter())
+        .map(|(node_list, edge_list)| {
+            subgraph::build(graph, node_list, edge_list).expect("components are closed under their edges")
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::Graph;
+    use super::*;
+
+    // two sentences, "dogs bark" and "cats sleep soundly", in one graph
+    fn document() -> Graph {
+        let mut graph = Graph::new();
+        let bark = graph.add_node("bark".to_string(), None);
+        let sleep = graph.add_node("sleep".to_string(), None);
+        let dogs = graph.add_node("dogs".to_string(), None);
+        let cats = graph.add_node("cats".to_string(), None);
+        let soundly = graph.add_node("soundly".to_string(), None);
+        graph.add_edge(bark, dogs, "nsubj".to_string(), None);
+        graph.add_edge(sleep, cats, "nsubj".to_string(), None);
+        graph.add_edge(sleep, soundly, "advmod".to_string(), None);
+        graph
+    }
+
+    #[test]
+    fn labels() {
+        let graph = document();
+        assert_eq!(vec![Some(0), Some(1), Some(0), Some(1), Some(1)], component_labels(&graph));
+        assert_eq!(vec![vec![0, 2], vec![1, 3, 4]], weakly_connected_components(&graph));
+    }
+
+    #[test]
+    fn labels_follow_edges_backwards() {
+        let mut graph = document();
+        graph.add_edge(4, 2, "odd".to_string(), None);
+        assert_eq!(vec![vec![0, 1, 2, 3, 4]], weakly_connected_components(&graph));
+
+        graph.remove_node(2);
+        assert_eq!(vec![vec![0], vec![1, 3, 4]], weakly_connected_components(&graph));
+    }
+
+    #[test]
+    fn split_sentences() {
+        let graph = document();
+        let sentences = split(&graph);
+        assert_eq!(2, sentences.len());
+
+        assert_eq!(vec![0, 2], sentences[0].nodes);
+        assert_eq!(vec![0], sentences[0].edges);
+        assert_eq!(vec![1, 3, 4], sentences[1].nodes);
+        assert_eq!(vec![1, 2], sentences[1].edges);
+
+        let sentence = &sentences[1].graph;
+        assert_eq!("sleep", sentence.nodes[0].identifier);
+        assert_eq!(vec![1, 2], sentence.successors(0).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,206 @@
+use std::borrow::Borrow;
+use std::iter::FromIterator;
+use std::vec;
+use graph;
+use node;
+use error::{Error, unwrap};
+use matching::{Attributes, EqualityRequirement, Matches, MatchedComponents};
+use prepared::PreparedQuery;
+
+// The position of a graph in a corpus, or in the stream of graphs being matched.
+pub type GraphId = usize;
+
+// A collection of graphs searched together, such as one graph per sentence of
+// a document.
+pub struct Corpus<N = Attributes, E = Attributes> {
+    pub graphs: Vec<graph::Graph<N, E>>,
+}
+
+impl Corpus {
+    pub fn new() -> Corpus {
+        Corpus::default()
+    }
+}
+
+impl<N, E> Default for Corpus<N, E> {
+    fn default() -> Corpus<N, E> {
+        Corpus { graphs: vec![] }
+    }
+}
+
+impl<N, E> Corpus<N, E> {
+    pub fn add_graph(&mut self, graph: graph::Graph<N, E>) -> GraphId {
+        self.graphs.push(graph);
+        self.graphs.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.graphs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.graphs.is_empty()
+    }
+}
+
+impl<N, E> FromIterator<graph::Graph<N, E>> for Corpus<N, E> {
+    fn from_iter<I: IntoIterator<Item = graph::Graph<N, E>>>(graphs: I) -> Corpus<N, E> {
+        Corpus { graphs: graphs.into_iter().collect() }
+    }
+}
+
+#[derive(Debug,PartialEq,Default)]
+pub struct Stats {
+    // the number of graphs searched
+    pub graphs: usize,
+    // the number of graphs with at least one match
+    pub matched_graphs: usize,
+    pub hits: usize,
+    // indexed by graph id
+    pub hits_per_graph: Vec<usize>,
+}
+
+impl Stats {
+    pub fn new(graphs: usize, matches: &[(GraphId, MatchedComponents)]) -> Stats {
+        let mut stats = Stats { graphs, matched_graphs: 0, hits: 0, hits_per_graph: vec![0; graphs] };
+        for &(graph_id, _) in matches {
+            stats.record(graph_id, 1);
+        }
+        stats
+    }
+
+    fn record(&mut self, graph_id: GraphId, hits: usize) {
+        if graph_id >= self.hits_per_graph.len() {
+            self.hits_per_graph.resize(graph_id + 1, 0);
+        }
+        if hits > 0 && self.hits_per_graph[graph_id] == 0 {
+            self.matched_graphs += 1;
+        }
+        self.hits_per_graph[graph_id] += hits;
+        self.hits += hits;
+    }
+}
+
+// Matches a query against a stream of graphs one graph at a time, so that the
+// graphs can be read lazily and dropped once they have been searched. The
+// query is prepared once for the whole stream. Graphs are numbered in the
+// order they are read. Stats cover the graphs read so far.
+pub struct CorpusMatches<'q, QN: 'q, QE: 'q, N, E, I> {
+    prepared: PreparedQuery<'q, QN, QE, N, E>,
+    graphs: I,
+    pending: vec::IntoIter<MatchedComponents>,
+    stats: Stats,
+}
+
+impl<'q, QN, QE, N, E, G, I> CorpusMatches<'q, QN, QE, N, E, I>
+    where QN: Matches<N>, QE: Matches<E>, G: Borrow<graph::Graph<N, E>>, I: Iterator<Item = G> {
+        pub fn new<T>(query: &'q graph::Graph<QN, QE>, query_root_index: node::Index, graphs: T, equality: &'q EqualityRequirement)
+            -> CorpusMatches<'q, QN, QE, N, E, I>
+            where T: IntoIterator<Item = G, IntoIter = I> {
+                unwrap(CorpusMatches::try_new(query, query_root_index, graphs, equality))
+            }
+
+        // The query is checked once, up front.
+        pub fn try_new<T>(query: &'q graph::Graph<QN, QE>, query_root_index: node::Index, graphs: T, equality: &'q EqualityRequirement)
+            -> Result<CorpusMatches<'q, QN, QE, N, E, I>, Error>
+            where T: IntoIterator<Item = G, IntoIter = I> {
+                Ok(CorpusMatches {
+                    prepared: PreparedQuery::try_new(query, query_root_index, equality)?,
+                    graphs: graphs.into_iter(),
+                    pending: vec![].into_iter(),
+                    stats: Stats::default(),
+                })
+            }
+
+        pub fn stats(&self) -> &Stats {
+            &self.stats
+        }
+    }
+
+impl<'q, QN, QE, N, E, G, I> Iterator for CorpusMatches<'q, QN, QE, N, E, I>
+    where QN: Matches<N>, QE: Matches<E>, G: Borrow<graph::Graph<N, E>>, I: Iterator<Item = G> {
+        type Item = (GraphId, MatchedComponents);
+
+        fn next(&mut self) -> Option<(GraphId, MatchedComponents)> {
+            loop {
+                if let Some(matched_components) = self.pending.next() {
+                    return Some((self.stats.graphs - 1, matched_components));
+                }
+                let graph = self.graphs.next()?;
+                let matches = self.prepared.execute(graph.borrow(), None);
+                let graph_id = self.stats.graphs;
+                self.stats.graphs += 1;
+                self.stats.record(graph_id, matches.len());
+                self.pending = matches.into_iter();
+            }
+        }
+    }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use graph::Graph;
+    use super::*;
+
+    fn word(word: &str) -> Attributes {
+        let mut attributes = HashMap::new();
+        attributes.insert("word".to_string(), word.to_string());
+        Some(attributes)
+    }
+
+    fn sentence(words: &[&str]) -> Graph {
+        let mut graph = Graph::new();
+        for (n, text) in words.iter().enumerate() {
+            graph.add_node(format!("token{}", n), word(text));
+        }
+        for n in 1..words.len() {
+            graph.add_edge(0, n, "dep".to_string(), None);
+        }
+        graph
+    }
+
+    fn query() -> Graph {
+        let mut query = Graph::new();
+        query.add_node("subject".to_string(), word("dogs"));
+        query
+    }
+
+    #[test]
+    fn corpus_matches() {
+        let corpus: Corpus = vec![
+            sentence(&["bark", "dogs"]),
+            sentence(&["sleep", "cats"]),
+            sentence(&["chase", "dogs", "dogs"]),
+        ].into_iter().collect();
+        let matches = ::match_corpus(&query(), 0, &corpus, &EqualityRequirement::Complete);
+        assert_eq!(vec![(0, 1), (2, 1), (2, 2)],
+                   matches.iter().map(|&(graph_id, ref matched)| (graph_id, matched.list[0].node)).collect::<Vec<_>>());
+
+        let stats = Stats::new(corpus.len(), &matches);
+        assert_eq!(3, stats.graphs);
+        assert_eq!(2, stats.matched_graphs);
+        assert_eq!(3, stats.hits);
+        assert_eq!(vec![1, 0, 2], stats.hits_per_graph);
+    }
+
+    #[test]
+    fn streaming() {
+        let query = query();
+        let graphs = (0..4).map(|n| if n % 2 == 0 { sentence(&["bark", "dogs"]) } else { sentence(&["sleep"]) });
+        let mut matches = CorpusMatches::new(&query, 0, graphs, &EqualityRequirement::Complete);
+        assert_eq!(0, matches.next().unwrap().0);
+        assert_eq!(1, matches.stats().graphs);
+        assert_eq!(2, matches.next().unwrap().0);
+        assert_eq!(None, matches.next());
+        assert_eq!(&Stats { graphs: 4, matched_graphs: 2, hits: 2, hits_per_graph: vec![1, 0, 1, 0] }, matches.stats());
+    }
+
+    #[test]
+    fn invalid_query() {
+        let mut query = query();
+        query.add_edge(0, 0, "loop".to_string(), None);
+        let corpus = Corpus::new();
+        assert_eq!(Some(Error::Cycle(vec![0, 0])), ::try_match_corpus(&query, 0, &corpus, &EqualityRequirement::Complete).err());
+        assert_eq!(Some(Error::InvalidQueryRoot(3)), ::try_match_corpus(&query, 3, &corpus, &EqualityRequirement::Complete).err());
+    }
+}
@@ -0,0 +1,194 @@
+use graph;
+use node;
+use edge;
+use paths::Path;
+
+// Finds a directed cycle by depth first search, returned as a path that starts
+// and ends at the same node. Self loops count as cycles.
+pub fn find_cycle<N, E>(graph: &graph::Graph<N, E>) -> Option<Path> {
+    // 0 is unvisited, 1 is on the current search path and 2 is finished
+    let mut states = vec![0u8; graph.nodes.len()];
+    for root in graph.node_indices() {
+        if states[root] != 0 {
+            continue;
+        }
+        // each entry is a node, the edge used to reach it and its unexplored edges
+        let mut stack: Vec<(node::Index, Option<edge::Index>, Vec<edge::Index>)> = vec![];
+        states[root] = 1;
+        stack.push((root, None, outgoing(graph, root)));
+
+        while let Some(&mut (_, _, ref mut edges)) = stack.last_mut() {
+            match edges.pop() {
+                Some(edge_index) => {
+                    let target = graph.edges[edge_index].target;
+                    match states[target] {
+                        0 => {
+                            states[target] = 1;
+                            stack.push((target, Some(edge_index), outgoing(graph, target)));
+                        }
+                        1 => return Some(close(graph, &stack, target, edge_index)),
+                        _ => {}
+                    }
+                }
+                None => {
+                    let (node_index, _, _) = stack.pop().expect("stack is not empty");
+                    states[node_index] = 2;
+                }
+            }
+        }
+    }
+    None
+}
+
+fn outgoing<N, E>(graph: &graph::Graph<N, E>, node_index: node::Index) -> Vec<edge::Index> {
+    let mut edges = graph.edges_for_node(node_index);
+    edges.reverse();
+    edges
+}
+
+// Builds the cycle from the part of the search path starting at target, closed
+// by the edge leading back to it.
+fn close<N, E>(graph: &graph::Graph<N, E>,
+               stack: &[(node::Index, Option<edge::Index>, Vec<edge::Index>)],
+               target: node::Index,
+               closing_edge: edge::Index)
+               -> Path {
+    let start = stack.iter().position(|&(node_index, _, _)| node_index == target)
+        .expect("target is on the search path");
+    let mut path = Path { nodes: vec![target], edges: vec![] };
+    for &(node_index, edge_index, _) in &stack[start + 1..] {
+        path.nodes.push(node_index);
+        path.edges.push(edge_index.expect("only the root has no edge"));
+    }
+    path.nodes.push(graph.edges[closing_edge].target);
+    path.edges.push(closing_edge);
+    path
+}
+
+// Tarjan's algorithm, without recursion. Components are listed in reverse
+// topological order, each node appears in exactly one of them.
+pub fn strongly_connected_components<N, E>(graph: &graph::Graph<N, E>) -> Vec<Vec<node::Index>> {
+    let count = graph.nodes.len();
+    let mut indexes: Vec<Option<usize>> = vec![None; count];
+    let mut low_links = vec![0; count];
+    let mut on_stack = vec![false; count];
+    let mut component_stack: Vec<node::Index> = vec![];
+    let mut components = vec![];
+    let mut next_index = 0;
+
+    for root in graph.node_indices() {
+        if indexes[root].is_some() {
+            continue;
+        }
+        let mut search: Vec<(node::Index, Vec<edge::Index>)> = vec![];
+        indexes[root] = Some(next_index);
+        low_links[root] = next_index;
+        next_index += 1;
+        component_stack.push(root);
+        on_stack[root] = true;
+        search.push((root, outgoing(graph, root)));
+
+        while let Some(&mut (node_index, ref mut edges)) = search.last_mut() {
+            match edges.pop() {
+                Some(edge_index) => {
+                    let target = graph.edges[edge_index].target;
+                    match indexes[target] {
+                        None => {
+                            indexes[target] = Some(next_index);
+                            low_links[target] = next_index;
+                            next_index += 1;
+                            component_stack.push(target);
+                            on_stack[target] = true;
+                            search.push((target, outgoing(graph, target)));
+                        }
+                        Some(target_index) => {
+                            if on_stack[target] && target_index < low_links[node_index] {
+                                low_links[node_index] = target_index;
+                            }
+                        }
+                    }
+                }
+                None => {
+                    search.pop();
+                    if let Some(&(parent, _)) = search.last() {
+                        if low_links[node_index] < low_links[parent] {
+                            low_links[parent] = low_links[node_index];
+                        }
+                    }
+                    if Some(low_links[node_index]) == indexes[node_index] {
+                        let mut component = vec![];
+                        loop {
+                            let member = component_stack.pop().expect("node is on the stack");
+                            on_stack[member] = false;
+                            component.push(member);
+                            if member == node_index {
+                                break;
+                            }
+                        }
+                        component.reverse();
+                        components.push(component);
+                    }
+                }
+            }
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::Graph;
+    use super::*;
+
+    fn chain(length: usize) -> Graph {
+        let mut graph = Graph::new();
+        for n in 0..length {
+            graph.add_node(format!("node{}", n), None);
+        }
+        for n in 1..length {
+            graph.add_edge(n - 1, n, "edge".to_string(), None);
+        }
+        graph
+    }
+
+    #[test]
+    fn acyclic() {
+        let graph = chain(4);
+        assert_eq!(None, find_cycle(&graph));
+        assert!(graph.is_acyclic());
+    }
+
+    #[test]
+    fn cycle() {
+        let mut graph = chain(4);
+        graph.add_edge(3, 1, "back".to_string(), None);
+        let cycle = find_cycle(&graph).unwrap();
+        assert_eq!(vec![1, 2, 3, 1], cycle.nodes);
+        assert_eq!(vec![1, 2, 3], cycle.edges);
+        assert!(!graph.is_acyclic());
+
+        let mut graph = chain(2);
+        graph.add_edge(1, 1, "loop".to_string(), None);
+        assert_eq!(vec![1, 1], find_cycle(&graph).unwrap().nodes);
+    }
+
+    #[test]
+    fn components() {
+        let mut graph = chain(5);
+        graph.add_edge(2, 1, "back".to_string(), None);
+        graph.add_edge(4, 3, "back".to_string(), None);
+        let mut components = strongly_connected_components(&graph);
+        components.sort();
+        assert_eq!(vec![vec![0], vec![1, 2], vec![3, 4]], components);
+    }
+
+    #[test]
+    fn long_chain() {
+        let mut graph = chain(100000);
+        assert_eq!(None, find_cycle(&graph));
+        assert_eq!(100000, strongly_connected_components(&graph).len());
+        graph.add_edge(99999, 0, "back".to_string(), None);
+        assert_eq!(100001, find_cycle(&graph).unwrap().nodes.len());
+        assert_eq!(1, strongly_connected_components(&graph).len());
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::{BTreeSet, HashMap};
+use graph;
+use node;
+use edge;
+use matching::AttributePairs;
+use symbols::Symbol;
+use view::GraphView;
+
+// How the nodes of the old graph are paired with those of the new one.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum NodeMatching {
+    // a node is the node with the same index, and likewise for edges
+    ByIndex,
+    // a node is the node with the same identifier, nodes sharing an identifier
+    // being paired in index order, and an edge is the edge with the same
+    // identifier between the same nodes. Identifiers then never change.
+    ByIdentifier,
+}
+
+#[derive(Debug,PartialEq)]
+pub struct AttributeChange {
+    pub key: String,
+    // None where the attribute is missing
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Debug,PartialEq)]
+pub struct NodeChange {
+    pub old: node::Index,
+    pub new: node::Index,
+    // the old and new identifier, when they differ
+    pub identifier: Option<(Symbol, Symbol)>,
+    pub attributes: Vec<AttributeChange>,
+}
+
+#[derive(Debug,PartialEq)]
+pub struct EdgeChange {
+    pub old: edge::Index,
+    pub new: edge::Index,
+    pub identifier: Option<(Symbol, Symbol)>,
+    // the old and new target, when the edge was retargeted
+    pub target: Option<(node::Index, node::Index)>,
+    pub attributes: Vec<AttributeChange>,
+}
+
+// What changed from one graph to another. Added nodes and edges are indexes
+// into the new graph, removed ones indexes into the old graph. Removed entries
+// are not compared, a node removed from a graph is simply missing from it.
+#[derive(Debug,PartialEq,Default)]
+pub struct Diff {
+    pub added_nodes: Vec<node::Index>,
+    pub removed_nodes: Vec<node::Index>,
+    pub changed_nodes: Vec<NodeChange>,
+    pub added_edges: Vec<edge::Index>,
+    pub removed_edges: Vec<edge::Index>,
+    pub changed_edges: Vec<EdgeChange>,
+    // old nodes whose paired outgoing edges are in a different order in the
+    // new graph
+    pub reordered_nodes: Vec<node::Index>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty() && self.removed_nodes.is_empty() && self.changed_nodes.is_empty() &&
+            self.added_edges.is_empty() && self.removed_edges.is_empty() && self.changed_edges.is_empty() &&
+            self.reordered_nodes.is_empty()
+    }
+}
+
+pub fn diff<N, E>(old: &graph::Graph<N, E>, new: &graph::Graph<N, E>, node_matching: NodeMatching) -> Diff
+    where N: AttributePairs, E: AttributePairs {
+        let mut result = Diff::default();
+
+        // the new node paired with each old node
+        let mut paired = vec![None; old.nodes.len()];
+        let mut new_paired = vec![false; new.nodes.len()];
+        match node_matching {
+            NodeMatching::ByIndex => {
+                for index in old.node_indices() {
+                    if new.contains_node(index) {
+                        paired[index] = Some(index);
+                        new_paired[index] = true;
+                    }
+                }
+            }
+            NodeMatching::ByIdentifier => {
+                let mut by_identifier: HashMap<&Symbol, Vec<node::Index>> = HashMap::new();
+                for index in new.node_indices().into_iter().rev() {
+                    by_identifier.entry(&new.nodes[index].identifier).or_default().push(index);
+                }
+                for index in old.node_indices() {
+                    let next = by_identifier.get_mut(&old.nodes[index].identifier).and_then(|indexes| indexes.pop());
+                    if let Some(new_index) = next {
+                        paired[index] = Some(new_index);
+                        new_paired[new_index] = true;
+                    }
+                }
+            }
+        }
+        for index in old.node_indices() {
+            match paired[index] {
+                Some(new_index) => {
+                    let (old_node, new_node) = (&old.nodes[index], &new.nodes[new_index]);
+                    let change = NodeChange {
+                        old: index,
+                        new: new_index,
+                        identifier: changed(&old_node.identifier, &new_node.identifier),
+                        attributes: attribute_changes(&old_node.attributes, &new_node.attributes),
+                    };
+                    if change.identifier.is_some() || !change.attributes.is_empty() {
+                        result.changed_nodes.push(change);
+                    }
+                }
+                None => result.removed_nodes.push(index),
+            }
+        }
+        result.added_nodes = new.node_indices().into_iter().filter(|&index| !new_paired[index]).collect();
+
+        let old_edges = live_edges(old);
+        let new_edges = live_edges(new);
+        let mut new_edge_paired = vec![false; new.edges.len()];
+        let mut edge_pairs = vec![];
+        match node_matching {
+            NodeMatching::ByIndex => {
+                for &index in &old_edges {
+                    let (old_edge, new_edge) = (&old.edges[index], new.edges.get(index));
+                    if let Some(new_edge) = new_edge {
+                        if !new_edge.removed && new_edge.source == old_edge.source {
+                            edge_pairs.push((index, index));
+                            new_edge_paired[index] = true;
+                        }
+                    }
+                }
+            }
+            NodeMatching::ByIdentifier => {
+                let mut by_key: HashMap<(node::Index, node::Index, &Symbol), Vec<edge::Index>> = HashMap::new();
+                for &index in new_edges.iter().rev() {
+                    let new_edge = &new.edges[index];
+                    by_key.entry((new_edge.source, new_edge.target, &new_edge.identifier)).or_default().push(index);
+                }
+                for &index in &old_edges {
+                    let old_edge = &old.edges[index];
+                    if let (Some(source), Some(target)) = (paired[old_edge.source], paired[old_edge.target]) {
+                        if let Some(new_index) = by_key.get_mut(&(source, target, &old_edge.identifier)).and_then(|indexes| indexes.pop()) {
+                            edge_pairs.push((index, new_index));
+                            new_edge_paired[new_index] = true;
+                        }
+                    }
+                }
+            }
+        }
+        let mut old_edge_paired = vec![None; old.edges.len()];
+        for &(index, new_index) in &edge_pairs {
+            old_edge_paired[index] = Some(new_index);
+            let (old_edge, new_edge) = (&old.edges[index], &new.edges[new_index]);
+            let change = EdgeChange {
+                old: index,
+                new: new_index,
+                identifier: changed(&old_edge.identifier, &new_edge.identifier),
+                target: if paired[old_edge.target] == Some(new_edge.target) { None } else { Some((old_edge.target, new_edge.target)) },
+                attributes: attribute_changes(&old_edge.attributes, &new_edge.attributes),
+            };
+            if change.identifier.is_some() || change.target.is_some() || !change.attributes.is_empty() {
+                result.changed_edges.push(change);
+            }
+        }
+        result.removed_edges = old_edges.into_iter().filter(|&index| old_edge_paired[index].is_none()).collect();
+        result.added_edges = new_edges.into_iter().filter(|&index| !new_edge_paired[index]).collect();
+
+        // paired edges leave paired nodes, so each node's are compared in turn
+        for index in old.node_indices() {
+            if let Some(new_index) = paired[index] {
+                let old_order = old.outgoing(index).filter_map(|edge_index| old_edge_paired[edge_index]);
+                let new_order = new.outgoing(new_index).filter(|&edge_index| new_edge_paired[edge_index]);
+                if !old_order.eq(new_order) {
+                    result.reordered_nodes.push(index);
+                }
+            }
+        }
+        result
+    }
+
+// Whether diff by index would find no differences, with payloads compared by
+// == rather than by their attribute pairs. See Graph's PartialEq.
+pub(crate) fn equal<N: PartialEq, E: PartialEq>(old: &graph::Graph<N, E>, new: &graph::Graph<N, E>) -> bool {
+    let node_indices = old.node_indices();
+    let edge_indices = live_edges(old);
+    node_indices == new.node_indices() && edge_indices == live_edges(new) &&
+        node_indices.iter().all(|&index| {
+            let (old_node, new_node) = (&old.nodes[index], &new.nodes[index]);
+            old_node.identifier == new_node.identifier && old_node.attributes == new_node.attributes &&
+                old.outgoing(index).eq(new.outgoing(index))
+        }) &&
+        edge_indices.iter().all(|&index| {
+            let (old_edge, new_edge) = (&old.edges[index], &new.edges[index]);
+            old_edge.identifier == new_edge.identifier && old_edge.source == new_edge.source &&
+                old_edge.target == new_edge.target && old_edge.attributes == new_edge.attributes
+        })
+}
+
+fn live_edges<N, E>(graph: &graph::Graph<N, E>) -> Vec<edge::Index> {
+    (0..graph.edges.len()).filter(|&index| !graph.edges[index].removed).collect()
+}
+
+fn changed(old: &Symbol, new: &Symbol) -> Option<(Symbol, Symbol)> {
+    if old == new { None } else { Some((old.clone(), new.clone())) }
+}
+
+// in key order
+fn attribute_changes<A: AttributePairs>(old: &A, new: &A) -> Vec<AttributeChange> {
+    let old: HashMap<String, String> = old.attribute_pairs().into_iter().collect();
+    let new: HashMap<String, String> = new.attribute_pairs().into_iter().collect();
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter(|&key| old.get(key) != new.get(key))
+        .map(|key| AttributeChange { key: key.clone(), old: old.get(key).cloned(), new: new.get(key).cloned() })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use graph::Graph;
+    use matching::Attributes;
+    use super::*;
+
+    fn word(word: &str) -> Attributes {
+        let mut attributes = HashMap::new();
+        attributes.insert("word".to_string(), word.to_string());
+        Some(attributes)
+    }
+
+    fn sentence() -> Graph {
+        let mut graph = Graph::new();
+        graph.add_node("see", word("see"));
+        graph.add_node("I", word("I"));
+        graph.add_node("dog", word("dog"));
+        graph.add_edge(0, 1, "nsubj", None);
+        graph.add_edge(0, 2, "dobj", None);
+        graph
+    }
+
+    #[test]
+    fn equality() {
+        assert_eq!(sentence(), sentence());
+        let mut other = sentence();
+        other.set_node_attribute(2, "word", "cat");
+        assert!(sentence() != other);
+        let mut reordered = Graph::new();
+        reordered.add_node("see", word("see"));
+        reordered.add_node("I", word("I"));
+        reordered.add_node("dog", word("dog"));
+        reordered.add_edge(0, 2, "dobj", None);
+        reordered.add_edge(0, 1, "nsubj", None);
+        assert!(sentence() != reordered);
+        assert!(!diff(&sentence(), &reordered, NodeMatching::ByIndex).is_empty());
+
+        // removed entries and link fields are not compared
+        let mut tombstones = sentence();
+        let extra = tombstones.add_node("extra", None);
+        tombstones.add_edge(extra, 0, "dep", None);
+        tombstones.remove_node(extra);
+        assert_eq!(sentence(), tombstones);
+        assert!(diff(&sentence(), &tombstones, NodeMatching::ByIndex).is_empty());
+
+        // the same edges listed in another order
+        let ranked = || {
+            let mut graph = sentence();
+            graph.set_edge_attribute(0, "rank", "2");
+            graph.set_edge_attribute(1, "rank", "1");
+            graph
+        };
+        let mut ordered = ranked();
+        let mut unordered = ranked();
+        ordered.order_edges_by_attribute("rank");
+        assert_eq!(vec![1, 0], ordered.edges_for_node(0));
+        assert!(ordered != unordered);
+        let changes = diff(&unordered, &ordered, NodeMatching::ByIndex);
+        assert_eq!(vec![0], changes.reordered_nodes);
+        assert!(changes.changed_edges.is_empty());
+        unordered.order_edges_by_attribute("rank");
+        assert_eq!(ordered, unordered);
+        assert!(diff(&unordered, &ordered, NodeMatching::ByIdentifier).is_empty());
+    }
+
+    #[test]
+    fn by_index() {
+        assert!(diff(&sentence(), &sentence(), NodeMatching::ByIndex).is_empty());
+
+        let mut new = sentence();
+        new.set_node_attribute(2, "word", "cat");
+        new.set_node_attribute(2, "pos", "NN");
+        new.set_node_identifier(2, "cat");
+        new.remove_edge(0);
+        new.add_node("quickly", None);
+        new.add_edge(0, 3, "advmod", None);
+        new.retarget_edge(1, 1);
+
+        let changes = diff(&sentence(), &new, NodeMatching::ByIndex);
+        assert_eq!(vec![3], changes.added_nodes);
+        assert!(changes.removed_nodes.is_empty());
+        assert_eq!(vec![NodeChange {
+            old: 2,
+            new: 2,
+            identifier: Some(("dog".into(), "cat".into())),
+            attributes: vec![
+                AttributeChange { key: "pos".to_string(), old: None, new: Some("NN".to_string()) },
+                AttributeChange { key: "word".to_string(), old: Some("dog".to_string()), new: Some("cat".to_string()) },
+            ],
+        }], changes.changed_nodes);
+        assert_eq!(vec![0], changes.removed_edges);
+        assert_eq!(vec![2], changes.added_edges);
+        assert_eq!(vec![EdgeChange { old: 1, new: 1, identifier: None, target: Some((2, 1)), attributes: vec![] }],
+                   changes.changed_edges);
+    }
+
+    #[test]
+    fn by_identifier() {
+        // the same sentence with its words added in another order
+        let mut new = Graph::new();
+        new.add_node("dog", word("dog"));
+        new.add_node("see", word("see"));
+        new.add_node("you", word("you"));
+        new.add_edge(1, 0, "dobj", None);
+        new.add_edge(1, 2, "nsubj", None);
+
+        let changes = diff(&sentence(), &new, NodeMatching::ByIdentifier);
+        assert_eq!(vec![2], changes.added_nodes);
+        assert_eq!(vec![1], changes.removed_nodes);
+        assert!(changes.changed_nodes.is_empty());
+        assert_eq!(vec![0], changes.removed_edges);
+        assert_eq!(vec![1], changes.added_edges);
+        assert!(changes.changed_edges.is_empty());
+        assert!(!diff(&sentence(), &new, NodeMatching::ByIndex).is_empty());
+    }
+}
@@ -1,41 +1,25 @@
-use std::collections::HashMap;
 use node;
-use matching;
-use matching::EqualityRequirement;
+use matching::{Attributes, EqualityRequirement, Matches};
+use symbols::Symbol;
 
 pub type Index = usize;
 
-pub struct Edge {
-    pub identifier: String,
+#[derive(Debug,PartialEq,Eq)]
+pub struct Edge<E = Attributes> {
+    pub identifier: Symbol,
+    pub source: node::Index,
     pub target: node::Index,
-    pub attributes: Option<HashMap<String, String>>,
+    pub attributes: E,
     pub next_outgoing_edge: Option<Index>,
+    pub next_incoming_edge: Option<Index>,
+    pub removed: bool,
 }
 
-impl Edge {
-    pub fn matches(&self, edge: &Edge, equality: &EqualityRequirement) -> bool {
-        match self.attributes {
-            Some(ref attrs) => {
-                match edge.attributes {
-                    Some(ref edge_attrs) => {
-                        for pair in attrs {
-                            match edge_attrs.get(pair.0) {
-                                Some(value) => {
-                                    if !matching::values_match(value, pair.1, &equality) {
-                                        return false;
-                                    }
-                                }
-                                None => return false,
-                            }
-                        }
-                    }
-                    None => return false,
-                }
-            }
-            None => return true,
+impl<E> Edge<E> {
+    pub fn matches<M>(&self, edge: &Edge<M>, equality: &EqualityRequirement) -> bool
+        where E: Matches<M> {
+            self.attributes.matches(&edge.attributes, equality)
         }
-        return true;
-    }
 }
 
 #[cfg(test)]
@@ -51,25 +35,34 @@ mod tests {
         attributes2.insert("core".to_string(), "worth".to_string());
 
         let edge0 = Edge {
-            identifier: "edgeid".to_string(),
+            identifier: "edgeid".into(),
+            source: 0,
             attributes: Some(attributes.clone()),
             next_outgoing_edge: None,
+            next_incoming_edge: None,
             target: 0,
+            removed: false,
         };
         let edge1 = Edge {
-            identifier: "edgeid".to_string(),
+            identifier: "edgeid".into(),
+            source: 0,
             attributes: Some(attributes.clone()),
             next_outgoing_edge: None,
+            next_incoming_edge: None,
             target: 0,
+            removed: false,
         };
         let edge2 = Edge {
-            identifier: "edgeid".to_string(),
+            identifier: "edgeid".into(),
+            source: 0,
             attributes: Some(attributes2.clone()),
             next_outgoing_edge: None,
+            next_incoming_edge: None,
             target: 0,
+            removed: false,
         };
         assert!(edge0.matches(&edge1, &EqualityRequirement::Complete));
-        assert_eq!(false, edge0.matches(&edge2, &EqualityRequirement::Complete));
+        assert!(!edge0.matches(&edge2, &EqualityRequirement::Complete));
     }
 
     #[test]
@@ -80,16 +73,22 @@ mod tests {
         attributes2.insert("key".to_string(), "the values".to_string());
 
         let edge0 = Edge {
-            identifier: "edgeid".to_string(),
+            identifier: "edgeid".into(),
+            source: 0,
             attributes: Some(attributes.clone()),
             next_outgoing_edge: None,
+            next_incoming_edge: None,
             target: 0,
+            removed: false,
         };
         let edge1 = Edge {
-            identifier: "edgeid".to_string(),
+            identifier: "edgeid".into(),
+            source: 0,
             attributes: Some(attributes2.clone()),
             next_outgoing_edge: None,
+            next_incoming_edge: None,
             target: 0,
+            removed: false,
         };
         assert!(edge0.matches(&edge1, &EqualityRequirement::Contains));
     }
@@ -0,0 +1,45 @@
+use std::error;
+use std::fmt;
+use node;
+use edge;
+
+#[derive(Debug,PartialEq)]
+pub enum Error {
+    InvalidNode(node::Index),
+    InvalidEdge(edge::Index),
+    InvalidQueryRoot(node::Index),
+    DuplicateIdentifier(String),
+    InvalidWeight(edge::Index),
+    NotATree(node::Index),
+    Cycle(Vec<node::Index>),
+    MissingAttribute(node::Index, String),
+    OverlappingMatch(node::Index),
+    NoFixpoint(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidNode(index) => write!(f, "node {} is not in the graph", index),
+            Error::InvalidEdge(index) => write!(f, "edge {} is not in the graph", index),
+            Error::InvalidQueryRoot(index) => write!(f, "query root {} is not in the query graph", index),
+            Error::DuplicateIdentifier(ref identifier) => write!(f, "a node with identifier {} is already in the graph", identifier),
+            Error::InvalidWeight(index) => write!(f, "edge {} has a negative or NaN weight", index),
+            Error::NotATree(index) => write!(f, "node {} has more than one parent or is on a cycle", index),
+            Error::Cycle(ref nodes) => write!(f, "graph contains a cycle through nodes {:?}", nodes),
+            Error::MissingAttribute(index, ref key) => write!(f, "node {} has no attribute {}", index, key),
+            Error::OverlappingMatch(index) => write!(f, "node {} is in more than one match", index),
+            Error::NoFixpoint(passes) => write!(f, "rules still change the graph after {} passes", passes),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+// Used by the panicking counterparts of the try_ functions.
+pub fn unwrap<T>(result: Result<T, Error>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) => panic!("{}", err),
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet};
+use graph;
+use node;
+use edge;
+use error::Error;
+
+pub type EdgeFilter<'a, E> = Box<dyn Fn(&edge::Edge<E>) -> bool + 'a>;
+pub type NodeFilter<'a, N> = Box<dyn Fn(&node::Node<N>) -> bool + 'a>;
+
+// Controls which parts of a graph expand_subgraph_with will follow. With no
+// options set every reachable node is listed once, in depth first order.
+pub struct ExpandOptions<'a, N: 'a, E: 'a> {
+    max_depth: Option<usize>,
+    allowed_identifiers: Option<Vec<String>>,
+    banned_identifiers: Vec<String>,
+    edge_filters: Vec<EdgeFilter<'a, E>>,
+    stop_filters: Vec<NodeFilter<'a, N>>,
+}
+
+impl<'a, N, E> Default for ExpandOptions<'a, N, E> {
+    fn default() -> ExpandOptions<'a, N, E> {
+        ExpandOptions {
+            max_depth: None,
+            allowed_identifiers: None,
+            banned_identifiers: vec![],
+            edge_filters: vec![],
+            stop_filters: vec![],
+        }
+    }
+}
+
+impl<'a, N, E> ExpandOptions<'a, N, E> {
+    pub fn new() -> ExpandOptions<'a, N, E> {
+        ExpandOptions::default()
+    }
+
+    // The root is at depth 0, nodes further than max_depth edges away are not listed.
+    pub fn max_depth(mut self, max_depth: usize) -> ExpandOptions<'a, N, E> {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    // Once an identifier is allowed, only edges with allowed identifiers are followed.
+    pub fn allow(mut self, identifier: &str) -> ExpandOptions<'a, N, E> {
+        self.allowed_identifiers.get_or_insert_with(Vec::new).push(identifier.to_string());
+        self
+    }
+
+    pub fn ban(mut self, identifier: &str) -> ExpandOptions<'a, N, E> {
+        self.banned_identifiers.push(identifier.to_string());
+        self
+    }
+
+    // Edges are only followed when every edge filter returns true for them.
+    pub fn edge_filter<F>(mut self, filter: F) -> ExpandOptions<'a, N, E>
+        where F: Fn(&edge::Edge<E>) -> bool + 'a {
+            self.edge_filters.push(Box::new(filter));
+            self
+        }
+
+    // Nodes for which a stop filter returns true are listed but not expanded.
+    pub fn stop_at<F>(mut self, filter: F) -> ExpandOptions<'a, N, E>
+        where F: Fn(&node::Node<N>) -> bool + 'a {
+            self.stop_filters.push(Box::new(filter));
+            self
+        }
+
+    pub fn follows(&self, edge: &edge::Edge<E>) -> bool {
+        if let Some(ref allowed) = self.allowed_identifiers {
+            if !allowed.iter().any(|identifier| *identifier == edge.identifier) {
+                return false;
+            }
+        }
+        !self.banned_identifiers.iter().any(|identifier| *identifier == edge.identifier) &&
+            self.edge_filters.iter().all(|filter| filter(edge))
+    }
+
+    pub fn stops_at(&self, node: &node::Node<N>) -> bool {
+        self.stop_filters.iter().any(|filter| filter(node))
+    }
+}
+
+// An explicit stack is used in place of recursion so that long chains cannot
+// overflow, and each node is listed only the first time it is reached, which
+// also stops cycles from being followed forever. A node first reached close to
+// the depth limit is expanded again if a shorter route to it is found later.
+pub fn expand<N, E>(graph: &graph::Graph<N, E>, root_index: node::Index, options: &ExpandOptions<N, E>)
+    -> Result<Vec<node::Index>, Error> {
+        expand_with_edges(graph, root_index, options).map(|(node_list, _)| node_list)
+    }
+
+// As expand, but also lists the edges that were followed, in the order they were
+// first followed.
+pub fn expand_with_edges<N, E>(graph: &graph::Graph<N, E>, root_index: node::Index, options: &ExpandOptions<N, E>)
+    -> Result<(Vec<node::Index>, Vec<edge::Index>), Error> {
+        graph.check_node(root_index)?;
+        let mut node_list = vec![];
+        let mut edge_list = vec![];
+        let mut followed: HashSet<edge::Index> = HashSet::new();
+        let mut depths: HashMap<node::Index, usize> = HashMap::new();
+        let mut stack = vec![(root_index, 0)];
+
+        while let Some((node_index, depth)) = stack.pop() {
+            match depths.get(&node_index) {
+                Some(&seen_depth) if seen_depth <= depth => continue,
+                Some(_) => {},
+                None => node_list.push(node_index),
+            }
+            depths.insert(node_index, depth);
+
+            if options.stops_at(&graph.nodes[node_index]) {
+                continue;
+            }
+            if let Some(max_depth) = options.max_depth {
+                if depth >= max_depth {
+                    continue;
+                }
+            }
+            let edges: Vec<edge::Index> = graph.edges_for_node(node_index).into_iter()
+                .filter(|&edge_index| options.follows(&graph.edges[edge_index]))
+                .collect();
+            for &edge_index in &edges {
+                if followed.insert(edge_index) {
+                    edge_list.push(edge_index);
+                }
+            }
+            for &edge_index in edges.iter().rev() {
+                stack.push((graph.edges[edge_index].target, depth + 1));
+            }
+        }
+
+        Ok((node_list, edge_list))
+    }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use graph::Graph;
+    use super::*;
+
+    fn diamond() -> Graph {
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), None);
+        let node1 = graph.add_node("node1".to_string(), None);
+        let node2 = graph.add_node("node2".to_string(), None);
+        let node3 = graph.add_node("node3".to_string(), None);
+        graph.add_edge(node0, node1, "left".to_string(), None);
+        graph.add_edge(node0, node2, "right".to_string(), None);
+        graph.add_edge(node1, node3, "edge".to_string(), None);
+        graph.add_edge(node2, node3, "edge".to_string(), None);
+        graph
+    }
+
+    #[test]
+    fn shared_descendants_listed_once() {
+        assert_eq!(vec![0, 1, 3, 2], expand(&diamond(), 0, &ExpandOptions::new()).unwrap());
+    }
+
+    #[test]
+    fn followed_edges() {
+        let (node_list, edge_list) = expand_with_edges(&diamond(), 0, &ExpandOptions::new()).unwrap();
+        assert_eq!(vec![0, 1, 3, 2], node_list);
+        assert_eq!(vec![0, 1, 2, 3], edge_list);
+    }
+
+    #[test]
+    fn cycles() {
+        let mut graph = diamond();
+        graph.add_edge(3, 0, "back".to_string(), None);
+        assert_eq!(vec![0, 1, 3, 2], expand(&graph, 0, &ExpandOptions::new()).unwrap());
+    }
+
+    #[test]
+    fn max_depth() {
+        let graph = diamond();
+        assert_eq!(vec![0], expand(&graph, 0, &ExpandOptions::new().max_depth(0)).unwrap());
+        assert_eq!(vec![0, 1, 2], expand(&graph, 0, &ExpandOptions::new().max_depth(1)).unwrap());
+    }
+
+    #[test]
+    fn allowed_and_banned_identifiers() {
+        let graph = diamond();
+        let options = ExpandOptions::new().allow("left").allow("edge");
+        assert_eq!(vec![0, 1, 3], expand(&graph, 0, &options).unwrap());
+        let options = ExpandOptions::new().ban("left");
+        assert_eq!(vec![0, 2, 3], expand(&graph, 0, &options).unwrap());
+    }
+
+    #[test]
+    fn edge_and_node_filters() {
+        let mut attributes = HashMap::new();
+        attributes.insert("weight".to_string(), "heavy".to_string());
+        let mut graph = diamond();
+        graph.add_edge(3, 0, "back".to_string(), Some(attributes));
+        graph.nodes[1].attributes = Some(HashMap::new());
+
+        let options = ExpandOptions::new()
+            .edge_filter(|edge: &edge::Edge| edge.attributes.is_none());
+        assert_eq!(vec![2, 3], expand(&graph, 2, &options).unwrap());
+
+        let options = ExpandOptions::new()
+            .ban("right")
+            .stop_at(|node: &node::Node| node.attributes.is_some());
+        assert_eq!(vec![0, 1], expand(&graph, 0, &options).unwrap());
+    }
+}
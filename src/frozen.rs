@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use graph::{self, EdgeOrder};
+use node;
+use edge;
+use error::{Error, unwrap};
+use index::AttributeIndex;
+use matching::Attributes;
+use view::{GraphView, IncomingEdges, OutgoingEdges};
+use symbols::{Symbol, SymbolTable};
+
+// A read only graph in compressed sparse row form: the outgoing edges of every
+// node sit next to each other in one vector, in the graph's order for them,
+// rather than in linked lists. Node and edge indexes are those of the graph it
+// was frozen from, removed nodes and edges included, so matches found in
+// either refer to the same nodes.
+//
+// Matching and the traversal iterators run on it through view::GraphView.
+// Any identifier or attribute index is kept and handed back by thaw.
+pub struct FrozenGraph<N = Attributes, E = Attributes> {
+    nodes: Vec<node::Node<N>>,
+    edges: Vec<edge::Edge<E>>,
+    // the outgoing edges of node n are adjacency[offsets[n]..offsets[n + 1]]
+    offsets: Vec<usize>,
+    adjacency: Vec<edge::Index>,
+    identifiers: Option<HashMap<Symbol, Vec<node::Index>>>,
+    unique_identifiers: bool,
+    attribute_index: Option<AttributeIndex<N>>,
+    symbols: Option<SymbolTable>,
+    edge_order: Option<EdgeOrder<E>>,
+}
+
+impl<N, E> FrozenGraph<N, E> {
+    pub(crate) fn from_parts(mut nodes: Vec<node::Node<N>>, mut edges: Vec<edge::Edge<E>>,
+                             identifiers: Option<HashMap<Symbol, Vec<node::Index>>>, unique_identifiers: bool,
+                             attribute_index: Option<AttributeIndex<N>>, symbols: Option<SymbolTable>,
+                             edge_order: Option<EdgeOrder<E>>)
+        -> FrozenGraph<N, E> {
+            let mut offsets = vec![0; nodes.len() + 1];
+            for edge in &edges {
+                if !edge.removed {
+                    offsets[edge.source + 1] += 1;
+                }
+            }
+            for n in 0..nodes.len() {
+                offsets[n + 1] += offsets[n];
+            }
+            let mut adjacency = Vec::with_capacity(offsets[nodes.len()]);
+            for node in &nodes {
+                if !node.removed {
+                    adjacency.extend(OutgoingEdges::Linked { edges: &edges, next: node.first_outgoing_edge });
+                }
+            }
+
+            // the lists are rebuilt by thaw
+            for node in &mut nodes {
+                node.first_outgoing_edge = None;
+            }
+            for edge in &mut edges {
+                edge.next_outgoing_edge = None;
+            }
+            FrozenGraph { nodes, edges, offsets, adjacency, identifiers, unique_identifiers, attribute_index, symbols, edge_order }
+        }
+
+    pub fn edges_for_node(&self, node_index: node::Index) -> &[edge::Index] {
+        unwrap(self.try_edges_for_node(node_index))
+    }
+
+    pub fn try_edges_for_node(&self, node_index: node::Index) -> Result<&[edge::Index], Error> {
+        self.check_node(node_index)?;
+        Ok(&self.adjacency[self.offsets[node_index]..self.offsets[node_index + 1]])
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    // As Graph::node_by_identifier, through the identifier index when the
+    // graph was frozen with one.
+    pub fn node_by_identifier(&self, identifier: &str) -> Option<node::Index> {
+        self.nodes_by_identifier(identifier).first().cloned()
+    }
+
+    pub fn nodes_by_identifier(&self, identifier: &str) -> Vec<node::Index> {
+        match self.identifiers {
+            Some(ref identifiers) => identifiers.get(identifier).cloned().unwrap_or_default(),
+            None => {
+                self.node_indices().into_iter()
+                    .filter(|&index| self.nodes[index].identifier == identifier)
+                    .collect()
+            }
+        }
+    }
+
+    // A mutable graph again, with its edge lists linked in the same order.
+    pub fn thaw(self) -> graph::Graph<N, E> {
+        let FrozenGraph { mut nodes, mut edges, adjacency, identifiers, unique_identifiers, attribute_index, symbols, edge_order, .. } = self;
+        for &edge_index in adjacency.iter().rev() {
+            let source = edges[edge_index].source;
+            edges[edge_index].next_outgoing_edge = nodes[source].first_outgoing_edge;
+            nodes[source].first_outgoing_edge = Some(edge_index);
+        }
+        graph::Graph::from_parts(nodes, edges, identifiers, unique_identifiers, attribute_index, symbols, edge_order)
+    }
+}
+
+impl<N, E> GraphView for FrozenGraph<N, E> {
+    type N = N;
+    type E = E;
+
+    fn nodes(&self) -> &[node::Node<N>] {
+        &self.nodes
+    }
+
+    fn edges(&self) -> &[edge::Edge<E>] {
+        &self.edges
+    }
+
+    fn outgoing(&self, node_index: node::Index) -> OutgoingEdges<'_, E> {
+        OutgoingEdges::Slice(self.adjacency[self.offsets[node_index]..self.offsets[node_index + 1]].iter())
+    }
+
+    fn incoming(&self, node_index: node::Index) -> IncomingEdges<'_, E> {
+        IncomingEdges::new(self.nodes[node_index].first_incoming_edge, &self.edges)
+    }
+
+    fn indexed_nodes(&self, pairs: &[(String, String)]) -> Option<Vec<node::Index>> {
+        match self.attribute_index {
+            Some(ref attribute_index) if !pairs.is_empty() => Some(attribute_index.nodes_with_all(pairs)),
+            _ => None,
+        }
+    }
+
+    fn indexed_node_bound(&self, pairs: &[(String, String)]) -> Option<usize> {
+        match self.attribute_index {
+            Some(ref attribute_index) if !pairs.is_empty() => Some(attribute_index.nodes_bound(pairs)),
+            _ => None,
+        }
+    }
+
+    fn out_degree(&self, node_index: node::Index) -> usize {
+        self.offsets[node_index + 1] - self.offsets[node_index]
+    }
+
+    fn has_outgoing(&self, node_index: node::Index, count: usize) -> bool {
+        self.out_degree(node_index) >= count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::Graph;
+    use super::*;
+
+    fn graph() -> Graph {
+        let mut graph = Graph::new();
+        for n in 0..4 {
+            graph.add_node(format!("node{}", n), None);
+        }
+        graph.add_edge(0, 1, "edge0".to_string(), None);
+        graph.add_edge(2, 3, "edge1".to_string(), None);
+        graph.add_edge(0, 2, "edge2".to_string(), None);
+        graph.add_edge(0, 3, "edge3".to_string(), None);
+        graph
+    }
+
+    #[test]
+    fn contiguous_edges() {
+        let mut graph = graph();
+        graph.remove_edge(2);
+        let frozen = graph.freeze();
+        assert_eq!(&[0, 3], frozen.edges_for_node(0));
+        assert_eq!(&[1], frozen.edges_for_node(2));
+        assert!(frozen.edges_for_node(3).is_empty());
+        assert_eq!(vec![1, 3], frozen.incoming(3).collect::<Vec<_>>());
+        assert_eq!(3, frozen.edge_count());
+        assert_eq!(4, frozen.node_count());
+        assert_eq!(Err(Error::InvalidNode(4)), frozen.try_edges_for_node(4));
+    }
+
+    #[test]
+    fn identifiers() {
+        let mut unindexed = graph();
+        unindexed.add_node("node2".to_string(), None);
+        assert_eq!(vec![2, 4], unindexed.freeze().nodes_by_identifier("node2"));
+        let mut graph = graph();
+        graph.index_identifiers(true).unwrap();
+        graph.remove_node(1);
+        let frozen = graph.freeze();
+        assert_eq!(Some(2), frozen.node_by_identifier("node2"));
+        assert_eq!(None, frozen.node_by_identifier("node1"));
+        assert!(frozen.nodes_by_identifier("missing").is_empty());
+    }
+
+    #[test]
+    fn thaw() {
+        let mut graph = graph();
+        graph.index_identifiers(true).unwrap();
+        graph.remove_edge(2);
+        let mut thawed = graph.freeze().thaw();
+        assert_eq!(vec![0, 3], thawed.edges_for_node(0));
+        assert_eq!(Some(2), thawed.node_by_identifier("node2"));
+        assert!(thawed.try_add_node("node2".to_string(), None).is_err());
+        thawed.add_edge(3, 0, "edge4".to_string(), None);
+        assert_eq!(vec![4], thawed.edges_for_node(3));
+    }
+}
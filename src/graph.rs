@@ -1,79 +1,781 @@
 use std::collections::HashMap;
+use std::fmt;
 use node;
 use edge;
+use error::{Error, unwrap};
+use cycles;
+use diff;
+use paths::Path;
+use index::{self, AttributeIndex};
+use matching::{Attributes, AttributePairs, AttributesMut};
+use frozen::FrozenGraph;
+use view::{GraphView, IncomingEdges, OutgoingEdges};
+use symbols::{InternedAttributes, Symbol, SymbolTable};
 
 // http://smallcultfollowing.com/babysteps/blog/2015/04/06/modeling-graphs-in-rust-using-vector-indices/
-pub struct Graph {
-    pub nodes: Vec<node::Node>,
-    pub edges: Vec<edge::Edge>,
+//
+// Nodes and edges carry a payload of type N and E respectively, by default the
+// string attribute maps used for matching. Other payload types can be matched
+// by implementing matching::Matches for them.
+//
+// The indexes, symbol table and edge order below are private, so graphs are
+// made with Graph::new or Graph::default rather than a struct literal.
+//
+// The optional identifier and attribute indexes are only kept up to date by the
+// methods on Graph, such as set_node_attribute. Nodes pushed onto the nodes
+// vector directly or attributes changed through the public fields are not
+// indexed.
+//
+// Each node's outgoing edges are kept in the order they were added, or by an
+// edge attribute after order_edges_by_attribute. Matching, traversals and
+// successors all follow that order.
+//
+// Identifiers are symbols::Symbol values. Once intern_identifiers is called the
+// graph keeps a symbol table, and nodes and edges sharing an identifier share
+// its string.
+pub struct Graph<N = Attributes, E = Attributes> {
+    pub nodes: Vec<node::Node<N>>,
+    pub edges: Vec<edge::Edge<E>>,
+    identifiers: Option<HashMap<Symbol, Vec<node::Index>>>,
+    unique_identifiers: bool,
+    attribute_index: Option<AttributeIndex<N>>,
+    symbols: Option<SymbolTable>,
+    edge_order: Option<EdgeOrder<E>>,
+}
+
+// Orders edges by the value of one of their attributes, see
+// Graph::order_edges_by_attribute.
+pub(crate) struct EdgeOrder<E> {
+    key: String,
+    attribute: fn(&E, &str) -> Option<String>,
+}
+
+// derived Clone would ask for E: Clone
+impl<E> Clone for EdgeOrder<E> {
+    fn clone(&self) -> EdgeOrder<E> {
+        EdgeOrder { key: self.key.clone(), attribute: self.attribute }
+    }
+}
+
+impl<E> EdgeOrder<E> {
+    fn sort_key(&self, edge: &edge::Edge<E>) -> SortKey {
+        match (self.attribute)(&edge.attributes, &self.key) {
+            Some(value) => {
+                match value.parse::<i64>() {
+                    Ok(number) => SortKey::Number(number),
+                    Err(_) => SortKey::Text(value),
+                }
+            }
+            None => SortKey::Missing,
+        }
+    }
+}
+
+// Integer values, such as token positions, come first and compare as numbers,
+// then other values compare as strings, and edges without the attribute come
+// last.
+#[derive(PartialEq,Eq,PartialOrd,Ord)]
+enum SortKey {
+    Number(i64),
+    Text(String),
+    Missing,
+}
+
+// payloads are interned in the graph's table when it keeps one
+fn set_attribute<A: AttributesMut>(attributes: &mut A, key: &str, value: &str, symbols: &mut Option<SymbolTable>) -> Option<String> {
+    match *symbols {
+        Some(ref mut symbols) => attributes.set_attribute_interned(key, value, symbols),
+        None => attributes.set_attribute(key, value),
+    }
+}
+
+// Graphs are equal when diff::diff by index finds nothing between them: the
+// same live nodes and edges at the same indexes, with equal identifiers,
+// endpoints and payloads, and each node's edges in the same order. Removed
+// entries, indexes and symbol tables are not compared.
+impl<N: PartialEq, E: PartialEq> PartialEq for Graph<N, E> {
+    fn eq(&self, other: &Graph<N, E>) -> bool {
+        diff::equal(self, other)
+    }
+}
+
+impl<N: Eq, E: Eq> Eq for Graph<N, E> {}
+
+impl<N: fmt::Debug, E: fmt::Debug> fmt::Debug for Graph<N, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Graph").field("nodes", &self.nodes).field("edges", &self.edges).finish()
+    }
 }
 
 impl Graph {
-    pub fn add_node(&mut self, identifier: String, attributes: Option<HashMap<String,String>>)
+    pub fn new() -> Graph {
+        Graph::default()
+    }
+}
+
+impl<N, E> Default for Graph<N, E> {
+    fn default() -> Graph<N, E> {
+        Graph { nodes: vec![], edges: vec![], identifiers: None, unique_identifiers: false, attribute_index: None, symbols: None, edge_order: None }
+    }
+}
+
+// Removal leaves a tombstone behind rather than shifting the vectors, so that
+// a node::Index or edge::Index is never reused for a different node or edge.
+// Removed entries are unlinked from the graph and are skipped by matching.
+//
+// Methods taking an index panic when it does not refer to a node or edge in the
+// graph, each has a try_ counterpart returning an Error instead.
+impl<N, E> Graph<N, E> {
+    pub fn add_node<S: Into<Symbol>>(&mut self, identifier: S, attributes: N)
         -> node::Index {
-            let index = self.nodes.len();
-            self.nodes.push(node::Node { identifier: identifier, first_outgoing_edge: None, attributes: attributes });
-            index
+            unwrap(self.try_add_node(identifier, attributes))
+        }
+
+    pub fn try_add_node<S: Into<Symbol>>(&mut self, identifier: S, attributes: N)
+        -> Result<node::Index, Error> {
+        let index = self.nodes.len();
+        let identifier = self.symbol(identifier.into());
+        if let Some(ref mut identifiers) = self.identifiers {
+            let indexes = identifiers.entry(identifier.clone()).or_default();
+            if self.unique_identifiers && !indexes.is_empty() {
+                return Err(Error::DuplicateIdentifier(identifier.to_string()));
+            }
+            indexes.push(index);
+        }
+        self.nodes.push(node::Node { identifier, first_outgoing_edge: None, first_incoming_edge: None, attributes, removed: false });
+        if let Some(ref mut attribute_index) = self.attribute_index {
+            attribute_index.insert_node(index, &self.nodes[index].attributes);
+        }
+        Ok(index)
+    }
+
+    // Builds an index from node identifiers to node indexes which add_node
+    // maintains from then on. When unique is set, adding a node with an
+    // identifier already in the graph fails with Error::DuplicateIdentifier,
+    // as does enabling the index on a graph that already has duplicates.
+    pub fn index_identifiers(&mut self, unique: bool) -> Result<(), Error> {
+        let mut identifiers: HashMap<Symbol, Vec<node::Index>> = HashMap::new();
+        for index in self.node_indices() {
+            let identifier = &self.nodes[index].identifier;
+            let indexes = identifiers.entry(identifier.clone()).or_default();
+            if unique && !indexes.is_empty() {
+                return Err(Error::DuplicateIdentifier(identifier.to_string()));
+            }
+            indexes.push(index);
         }
+        self.identifiers = Some(identifiers);
+        self.unique_identifiers = unique;
+        Ok(())
+    }
 
-    pub fn add_edge(&mut self, source: node::Index, target: node::Index, identifier: String, attributes: Option<HashMap<String,String>>) {
+    // Keeps a symbol table from now on, interning the identifiers of nodes and
+    // edges already in the graph and of those added later.
+    pub fn intern_identifiers(&mut self) {
+        let mut symbols = self.symbols.take().unwrap_or_default();
+        for node in &mut self.nodes {
+            node.identifier = symbols.intern(&node.identifier);
+        }
+        for edge in &mut self.edges {
+            edge.identifier = symbols.intern(&edge.identifier);
+        }
+        if let Some(ref mut identifiers) = self.identifiers {
+            *identifiers = identifiers.drain()
+                .map(|(identifier, indexes)| (symbols.intern(&identifier), indexes))
+                .collect();
+        }
+        self.symbols = Some(symbols);
+    }
+
+    // The graph's symbol for a string, interned in its table, which is
+    // started if the graph has none yet.
+    pub fn intern(&mut self, string: &str) -> Symbol {
+        self.symbols.get_or_insert_with(SymbolTable::new).intern(string)
+    }
+
+    // Attributes for a node or edge payload, interned in the graph's table.
+    pub fn intern_attributes(&mut self, attributes: &HashMap<String, String>) -> InternedAttributes {
+        self.symbols.get_or_insert_with(SymbolTable::new).intern_attributes(attributes)
+    }
+
+    pub fn symbol_table(&self) -> Option<&SymbolTable> {
+        self.symbols.as_ref()
+    }
+
+    fn symbol(&mut self, identifier: Symbol) -> Symbol {
+        match self.symbols {
+            Some(ref mut symbols) => symbols.intern(&identifier),
+            None => identifier,
+        }
+    }
+
+    pub fn node_by_identifier(&self, identifier: &str) -> Option<node::Index> {
+        self.nodes_by_identifier(identifier).first().cloned()
+    }
+
+    pub fn nodes_by_identifier(&self, identifier: &str) -> Vec<node::Index> {
+        match self.identifiers {
+            Some(ref identifiers) => identifiers.get(identifier).cloned().unwrap_or_default(),
+            None => {
+                self.node_indices().into_iter()
+                    .filter(|&index| self.nodes[index].identifier == identifier)
+                    .collect()
+            }
+        }
+    }
+
+    // Builds an index from node attribute pairs to node indexes, and from edge
+    // identifiers to edge indexes, which the methods adding and removing nodes
+    // and edges maintain from then on. Matching uses it to find candidate roots.
+    pub fn index_attributes(&mut self) where N: AttributePairs {
+        let mut attribute_index = AttributeIndex::new(N::attribute_pairs);
+        for index in self.node_indices() {
+            attribute_index.insert_node(index, &self.nodes[index].attributes);
+        }
+        for index in 0..self.edges.len() {
+            if !self.edges[index].removed {
+                attribute_index.insert_edge(index, &self.edges[index].identifier);
+            }
+        }
+        self.attribute_index = Some(attribute_index);
+    }
+
+    pub fn nodes_by_attribute(&self, key: &str, value: &str) -> Vec<node::Index> where N: AttributePairs {
+        match self.attribute_index {
+            Some(ref attribute_index) => attribute_index.nodes(key, value).to_vec(),
+            None => {
+                self.node_indices().into_iter()
+                    .filter(|&index| {
+                        self.nodes[index].attributes.attribute_pairs().iter()
+                            .any(|(k, v)| k == key && v == value)
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // The nodes having all of the given attribute pairs, in ascending order, or
+    // None when there is no attribute index or no pairs to look up.
+    pub fn indexed_nodes(&self, pairs: &[(String, String)]) -> Option<Vec<node::Index>> {
+        match self.attribute_index {
+            Some(ref attribute_index) if !pairs.is_empty() => Some(attribute_index.nodes_with_all(pairs)),
+            _ => None,
+        }
+    }
+
+    pub fn edges_by_identifier(&self, identifier: &str) -> Vec<edge::Index> {
+        match self.attribute_index {
+            Some(ref attribute_index) => attribute_index.edges(identifier).to_vec(),
+            None => {
+                (0..self.edges.len())
+                    .filter(|&index| !self.edges[index].removed && self.edges[index].identifier == identifier)
+                    .collect()
+            }
+        }
+    }
+
+    pub fn add_edge<S: Into<Symbol>>(&mut self, source: node::Index, target: node::Index, identifier: S, attributes: E)
+        -> edge::Index {
+            unwrap(self.try_add_edge(source, target, identifier, attributes))
+        }
+
+    pub fn try_add_edge<S: Into<Symbol>>(&mut self, source: node::Index, target: node::Index, identifier: S, attributes: E)
+        -> Result<edge::Index, Error> {
+        self.check_node(source)?;
+        self.check_node(target)?;
+        let identifier = self.symbol(identifier.into());
         let edge_index = self.edges.len();
-        let node_data = &mut self.nodes[source];
         self.edges.push(edge::Edge {
-            identifier: identifier,
-            target: target,
-            next_outgoing_edge: node_data.first_outgoing_edge,
-            attributes: attributes,
+            identifier,
+            source,
+            target,
+            next_outgoing_edge: None,
+            next_incoming_edge: None,
+            attributes,
+            removed: false,
         });
-        node_data.first_outgoing_edge = Some(edge_index);
+        self.link_edge(edge_index);
+        self.link_incoming_edge(edge_index);
+        if let Some(ref mut attribute_index) = self.attribute_index {
+            attribute_index.insert_edge(edge_index, &self.edges[edge_index].identifier);
+        }
+        Ok(edge_index)
+    }
+
+    // Links a new edge into its source's list, after the edges added before it
+    // unless the edge order places it earlier.
+    fn link_edge(&mut self, edge_index: edge::Index) {
+        let source = self.edges[edge_index].source;
+        let sort_key = self.edge_order.as_ref().map(|edge_order| edge_order.sort_key(&self.edges[edge_index]));
+        let mut previous: Option<edge::Index> = None;
+        let mut current = self.nodes[source].first_outgoing_edge;
+        while let Some(current_index) = current {
+            if let (Some(edge_order), Some(sort_key)) = (self.edge_order.as_ref(), sort_key.as_ref()) {
+                if edge_order.sort_key(&self.edges[current_index]) > *sort_key {
+                    break;
+                }
+            }
+            previous = current;
+            current = self.edges[current_index].next_outgoing_edge;
+        }
+        self.edges[edge_index].next_outgoing_edge = current;
+        match previous {
+            Some(previous) => self.edges[previous].next_outgoing_edge = Some(edge_index),
+            None => self.nodes[source].first_outgoing_edge = Some(edge_index),
+        }
+    }
+
+    // Incoming edges are listed in ascending edge index order, so the lists do
+    // not depend on the order edges were added or retargeted in.
+    fn link_incoming_edge(&mut self, edge_index: edge::Index) {
+        let target = self.edges[edge_index].target;
+        let mut previous: Option<edge::Index> = None;
+        let mut current = self.nodes[target].first_incoming_edge;
+        while let Some(current_index) = current {
+            if current_index > edge_index {
+                break;
+            }
+            previous = current;
+            current = self.edges[current_index].next_incoming_edge;
+        }
+        self.edges[edge_index].next_incoming_edge = current;
+        match previous {
+            Some(previous) => self.edges[previous].next_incoming_edge = Some(edge_index),
+            None => self.nodes[target].first_incoming_edge = Some(edge_index),
+        }
+    }
+
+    // Rebuilds every incoming edge list from the edges' targets, for graphs
+    // whose vectors were filled in directly.
+    pub(crate) fn relink_incoming_edges(&mut self) {
+        for node in &mut self.nodes {
+            node.first_incoming_edge = None;
+        }
+        for edge_index in (0..self.edges.len()).rev() {
+            let target = self.edges[edge_index].target;
+            if self.edges[edge_index].removed {
+                self.edges[edge_index].next_incoming_edge = None;
+                continue;
+            }
+            self.edges[edge_index].next_incoming_edge = self.nodes[target].first_incoming_edge;
+            self.nodes[target].first_incoming_edge = Some(edge_index);
+        }
+    }
+
+    // Orders each node's outgoing edges by the value of an edge attribute,
+    // keeping edges with equal values in the order they were added, and places
+    // edges added later by their value too. Integer values come before all
+    // others and are compared as numbers, so that token positions order as
+    // expected.
+    pub fn order_edges_by_attribute(&mut self, key: &str) where E: AttributePairs {
+        self.edge_order = Some(EdgeOrder { key: key.to_string(), attribute: E::attribute });
+        self.relink_edges();
+    }
+
+    // Orders outgoing edges the way another graph with the same edge payload
+    // does, for graphs made from parts of it.
+    pub(crate) fn order_edges_like<M>(&mut self, other: &Graph<M, E>) {
+        self.edge_order = other.edge_order.clone();
+        self.relink_edges();
+    }
+
+    // Goes back to keeping outgoing edges in the order they were added.
+    pub fn order_edges_by_insertion(&mut self) {
+        self.edge_order = None;
+        self.relink_edges();
+    }
+
+    fn relink_edges(&mut self) {
+        for node_index in self.node_indices() {
+            let mut edge_indexes = self.edges_for_node(node_index);
+            // edge indexes grow as edges are added
+            edge_indexes.sort();
+            if let Some(ref edge_order) = self.edge_order {
+                let edges = &self.edges;
+                // a stable sort, so equal values stay in the order added
+                edge_indexes.sort_by_cached_key(|&edge_index| edge_order.sort_key(&edges[edge_index]));
+            }
+            let mut next = None;
+            for &edge_index in edge_indexes.iter().rev() {
+                self.edges[edge_index].next_outgoing_edge = next;
+                next = Some(edge_index);
+            }
+            self.nodes[node_index].first_outgoing_edge = next;
+        }
+    }
+
+    pub fn contains_node(&self, node_index: node::Index) -> bool {
+        match self.nodes.get(node_index) {
+            Some(node) => !node.removed,
+            None => false,
+        }
+    }
+
+    pub fn contains_edge(&self, edge_index: edge::Index) -> bool {
+        match self.edges.get(edge_index) {
+            Some(edge) => !edge.removed,
+            None => false,
+        }
+    }
+
+    pub fn check_node(&self, node_index: node::Index) -> Result<(), Error> {
+        if self.contains_node(node_index) { Ok(()) } else { Err(Error::InvalidNode(node_index)) }
+    }
+
+    pub fn check_edge(&self, edge_index: edge::Index) -> Result<(), Error> {
+        if self.contains_edge(edge_index) { Ok(()) } else { Err(Error::InvalidEdge(edge_index)) }
+    }
+
+    pub fn is_acyclic(&self) -> bool {
+        self.find_cycle().is_none()
+    }
+
+    pub fn find_cycle(&self) -> Option<Path> {
+        cycles::find_cycle(self)
+    }
+
+    // Fails with Error::Cycle listing the nodes of a cycle when there is one.
+    pub fn check_acyclic(&self) -> Result<(), Error> {
+        match self.find_cycle() {
+            Some(cycle) => Err(Error::Cycle(cycle.nodes)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.iter().filter(|node| !node.removed).count()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.iter().filter(|edge| !edge.removed).count()
+    }
+
+    pub fn node_indices(&self) -> Vec<node::Index> {
+        (0..self.nodes.len()).filter(|&index| !self.nodes[index].removed).collect()
+    }
+
+    pub fn remove_edge(&mut self, edge_index: edge::Index) {
+        unwrap(self.try_remove_edge(edge_index))
+    }
+
+    pub fn try_remove_edge(&mut self, edge_index: edge::Index) -> Result<(), Error> {
+        self.check_edge(edge_index)?;
+        self.unlink_edge(edge_index);
+        self.unlink_incoming_edge(edge_index);
+        if let Some(ref mut attribute_index) = self.attribute_index {
+            attribute_index.remove_edge(edge_index, &self.edges[edge_index].identifier);
+        }
+        self.edges[edge_index].removed = true;
+        Ok(())
+    }
+
+    fn unlink_edge(&mut self, edge_index: edge::Index) {
+        let source = self.edges[edge_index].source;
+        let next = self.edges[edge_index].next_outgoing_edge;
+
+        if self.nodes[source].first_outgoing_edge == Some(edge_index) {
+            self.nodes[source].first_outgoing_edge = next;
+        } else {
+            let mut previous = self.nodes[source].first_outgoing_edge
+                .expect("edge is missing from its source node's edge list");
+            while self.edges[previous].next_outgoing_edge != Some(edge_index) {
+                previous = self.edges[previous].next_outgoing_edge
+                    .expect("edge is missing from its source node's edge list");
+            }
+            self.edges[previous].next_outgoing_edge = next;
+        }
+        self.edges[edge_index].next_outgoing_edge = None;
+    }
+
+    fn unlink_incoming_edge(&mut self, edge_index: edge::Index) {
+        let target = self.edges[edge_index].target;
+        let next = self.edges[edge_index].next_incoming_edge;
+
+        if self.nodes[target].first_incoming_edge == Some(edge_index) {
+            self.nodes[target].first_incoming_edge = next;
+        } else {
+            let mut previous = self.nodes[target].first_incoming_edge
+                .expect("edge is missing from its target node's edge list");
+            while self.edges[previous].next_incoming_edge != Some(edge_index) {
+                previous = self.edges[previous].next_incoming_edge
+                    .expect("edge is missing from its target node's edge list");
+            }
+            self.edges[previous].next_incoming_edge = next;
+        }
+        self.edges[edge_index].next_incoming_edge = None;
+    }
+
+    // Removes the node along with every edge leaving or entering it. Each edge
+    // is unlinked from the lists of the nodes at both of its ends, so the cost
+    // grows with the number of edges at the node and its neighbours rather
+    // than with the size of the graph.
+    pub fn remove_node(&mut self, node_index: node::Index) {
+        unwrap(self.try_remove_node(node_index))
+    }
+
+    pub fn try_remove_node(&mut self, node_index: node::Index) -> Result<(), Error> {
+        self.check_node(node_index)?;
+        for edge_index in self.edges_for_node(node_index) {
+            self.remove_edge(edge_index);
+        }
+        for edge_index in self.incoming_edges_for_node(node_index) {
+            self.remove_edge(edge_index);
+        }
+        self.unindex_identifier(node_index);
+        if let Some(ref mut attribute_index) = self.attribute_index {
+            attribute_index.remove_node(node_index, &self.nodes[node_index].attributes);
+        }
+        self.nodes[node_index].removed = true;
+        Ok(())
+    }
+
+    fn unindex_identifier(&mut self, node_index: node::Index) {
+        if let Some(ref mut identifiers) = self.identifiers {
+            let identifier = &self.nodes[node_index].identifier;
+            let now_empty = match identifiers.get_mut(identifier) {
+                Some(indexes) => {
+                    indexes.retain(|&index| index != node_index);
+                    indexes.is_empty()
+                }
+                None => false,
+            };
+            if now_empty {
+                identifiers.remove(identifier);
+            }
+        }
+    }
+
+    // The methods below change nodes and edges in place, keeping the
+    // identifier and attribute indexes and the edge order up to date. Setting
+    // or removing an attribute returns the value it had before.
+    pub fn set_node_attribute(&mut self, node_index: node::Index, key: &str, value: &str) -> Option<String>
+        where N: AttributesMut {
+            unwrap(self.try_set_node_attribute(node_index, key, value))
+        }
+
+    pub fn try_set_node_attribute(&mut self, node_index: node::Index, key: &str, value: &str)
+        -> Result<Option<String>, Error>
+        where N: AttributesMut {
+            self.check_node(node_index)?;
+            Ok(self.update_node_attributes(node_index, |attributes, symbols| set_attribute(attributes, key, value, symbols)))
+        }
+
+    pub fn remove_node_attribute(&mut self, node_index: node::Index, key: &str) -> Option<String>
+        where N: AttributesMut {
+            unwrap(self.try_remove_node_attribute(node_index, key))
+        }
+
+    pub fn try_remove_node_attribute(&mut self, node_index: node::Index, key: &str)
+        -> Result<Option<String>, Error>
+        where N: AttributesMut {
+            self.check_node(node_index)?;
+            Ok(self.update_node_attributes(node_index, |attributes, _| attributes.remove_attribute(key)))
+        }
+
+    fn update_node_attributes<F>(&mut self, node_index: node::Index, update: F) -> Option<String>
+        where F: FnOnce(&mut N, &mut Option<SymbolTable>) -> Option<String> {
+            if let Some(ref mut attribute_index) = self.attribute_index {
+                attribute_index.remove_node(node_index, &self.nodes[node_index].attributes);
+            }
+            let previous = update(&mut self.nodes[node_index].attributes, &mut self.symbols);
+            if let Some(ref mut attribute_index) = self.attribute_index {
+                attribute_index.insert_node(node_index, &self.nodes[node_index].attributes);
+            }
+            previous
+        }
+
+    pub fn set_edge_attribute(&mut self, edge_index: edge::Index, key: &str, value: &str) -> Option<String>
+        where E: AttributesMut {
+            unwrap(self.try_set_edge_attribute(edge_index, key, value))
+        }
+
+    pub fn try_set_edge_attribute(&mut self, edge_index: edge::Index, key: &str, value: &str)
+        -> Result<Option<String>, Error>
+        where E: AttributesMut {
+            self.check_edge(edge_index)?;
+            Ok(self.update_edge_attributes(edge_index, |attributes, symbols| set_attribute(attributes, key, value, symbols)))
+        }
+
+    pub fn remove_edge_attribute(&mut self, edge_index: edge::Index, key: &str) -> Option<String>
+        where E: AttributesMut {
+            unwrap(self.try_remove_edge_attribute(edge_index, key))
+        }
+
+    pub fn try_remove_edge_attribute(&mut self, edge_index: edge::Index, key: &str)
+        -> Result<Option<String>, Error>
+        where E: AttributesMut {
+            self.check_edge(edge_index)?;
+            Ok(self.update_edge_attributes(edge_index, |attributes, _| attributes.remove_attribute(key)))
+        }
+
+    // an edge whose attributes change moves to its place in the edge order
+    fn update_edge_attributes<F>(&mut self, edge_index: edge::Index, update: F) -> Option<String>
+        where F: FnOnce(&mut E, &mut Option<SymbolTable>) -> Option<String> {
+            let previous = update(&mut self.edges[edge_index].attributes, &mut self.symbols);
+            if self.edge_order.is_some() {
+                self.unlink_edge(edge_index);
+                self.link_edge(edge_index);
+            }
+            previous
+        }
+
+    // Points an edge at a different target node, leaving it in place among
+    // its source's edges.
+    pub fn retarget_edge(&mut self, edge_index: edge::Index, target: node::Index) {
+        unwrap(self.try_retarget_edge(edge_index, target))
     }
 
-    pub fn successors(&self, source: node::Index) -> Successors {
+    pub fn try_retarget_edge(&mut self, edge_index: edge::Index, target: node::Index) -> Result<(), Error> {
+        self.check_edge(edge_index)?;
+        self.check_node(target)?;
+        self.unlink_incoming_edge(edge_index);
+        self.edges[edge_index].target = target;
+        self.link_incoming_edge(edge_index);
+        Ok(())
+    }
+
+    // Fails with Error::DuplicateIdentifier when identifiers are indexed as
+    // unique and another node already has the new one.
+    pub fn set_node_identifier<S: Into<Symbol>>(&mut self, node_index: node::Index, identifier: S) {
+        unwrap(self.try_set_node_identifier(node_index, identifier))
+    }
+
+    pub fn try_set_node_identifier<S: Into<Symbol>>(&mut self, node_index: node::Index, identifier: S)
+        -> Result<(), Error> {
+            self.check_node(node_index)?;
+            let identifier = self.symbol(identifier.into());
+            if self.unique_identifiers && identifier != self.nodes[node_index].identifier &&
+               !self.nodes_by_identifier(&identifier).is_empty() {
+                return Err(Error::DuplicateIdentifier(identifier.to_string()));
+            }
+            self.unindex_identifier(node_index);
+            if let Some(ref mut identifiers) = self.identifiers {
+                index::insert_sorted(identifiers.entry(identifier.clone()).or_default(), node_index);
+            }
+            self.nodes[node_index].identifier = identifier;
+            Ok(())
+        }
+
+    pub fn set_edge_identifier<S: Into<Symbol>>(&mut self, edge_index: edge::Index, identifier: S) {
+        unwrap(self.try_set_edge_identifier(edge_index, identifier))
+    }
+
+    pub fn try_set_edge_identifier<S: Into<Symbol>>(&mut self, edge_index: edge::Index, identifier: S)
+        -> Result<(), Error> {
+            self.check_edge(edge_index)?;
+            let identifier = self.symbol(identifier.into());
+            if let Some(ref mut attribute_index) = self.attribute_index {
+                attribute_index.remove_edge(edge_index, &self.edges[edge_index].identifier);
+                attribute_index.insert_edge(edge_index, &identifier);
+            }
+            self.edges[edge_index].identifier = identifier;
+            Ok(())
+        }
+
+    pub fn successors(&self, source: node::Index) -> Successors<'_, N, E> {
+        unwrap(self.try_successors(source))
+    }
+
+    pub fn try_successors(&self, source: node::Index) -> Result<Successors<'_, N, E>, Error> {
+        self.check_node(source)?;
         let first_outgoing_edge = self.nodes[source].first_outgoing_edge;
-        Successors { graph: self, current_edge_index: first_outgoing_edge }
+        Ok(Successors { graph: self, current_edge_index: first_outgoing_edge })
     }
 
     pub fn edges_for_node(&self, node_index: node::Index) -> Vec<edge::Index> {
+        unwrap(self.try_edges_for_node(node_index))
+    }
+
+    pub fn try_edges_for_node(&self, node_index: node::Index) -> Result<Vec<edge::Index>, Error> {
+        self.check_node(node_index)?;
         let mut edge_indexes: Vec<edge::Index> = vec![];
-        match self.nodes[node_index].first_outgoing_edge {
-            Some(edge_index) => {
-                let mut edge = &self.edges[edge_index];
+        if let Some(edge_index) = self.nodes[node_index].first_outgoing_edge {
+            let mut edge = &self.edges[edge_index];
+            edge_indexes.push(edge_index);
+            while let Some(edge_index) = edge.next_outgoing_edge {
+                edge = &self.edges[edge_index];
                 edge_indexes.push(edge_index);
-                loop {
-                    match edge.next_outgoing_edge {
-                        Some(edge_index) => {
-                            edge = &self.edges[edge_index];
-                            edge_indexes.push(edge_index);
-                        },
-                        None => { break; }
-                    }
-                }
-            },
-            None => {}
+            }
         }
-        return edge_indexes;
+        Ok(edge_indexes)
     }
 
+    // The edges entering a node, in ascending edge index order.
+    pub fn incoming_edges_for_node(&self, node_index: node::Index) -> Vec<edge::Index> {
+        unwrap(self.try_incoming_edges_for_node(node_index))
+    }
+
+    pub fn try_incoming_edges_for_node(&self, node_index: node::Index) -> Result<Vec<edge::Index>, Error> {
+        self.check_node(node_index)?;
+        Ok(self.incoming(node_index).collect())
+    }
+
+    // An immutable copy of the graph with each node's outgoing edges stored
+    // together in the same order, see frozen::FrozenGraph.
+    pub fn freeze(self) -> FrozenGraph<N, E> {
+        let Graph { nodes, edges, identifiers, unique_identifiers, attribute_index, symbols, edge_order } = self;
+        FrozenGraph::from_parts(nodes, edges, identifiers, unique_identifiers, attribute_index, symbols, edge_order)
+    }
+
+    pub(crate) fn from_parts(nodes: Vec<node::Node<N>>, edges: Vec<edge::Edge<E>>,
+                             identifiers: Option<HashMap<Symbol, Vec<node::Index>>>, unique_identifiers: bool,
+                             attribute_index: Option<AttributeIndex<N>>, symbols: Option<SymbolTable>,
+                             edge_order: Option<EdgeOrder<E>>)
+        -> Graph<N, E> {
+            Graph { nodes, edges, identifiers, unique_identifiers, attribute_index, symbols, edge_order }
+        }
+
     pub fn print(self) {
-        for n in 0..self.nodes.len() {
+        for n in self.node_indices() {
             print!("node::Node {} goes to: ", n);
             let mut suc = self.successors(n);
             loop {
                 match suc.next() {
                     Some(s) => { print!("{}, ", s) },
-                    None => { print!("\n"); break },
+                    None => { println!(); break },
                 }
             }
         }
     }
 }
 
-pub struct Successors<'graph> {
-    graph: &'graph Graph,
+impl<N, E> GraphView for Graph<N, E> {
+    type N = N;
+    type E = E;
+
+    fn nodes(&self) -> &[node::Node<N>] {
+        &self.nodes
+    }
+
+    fn edges(&self) -> &[edge::Edge<E>] {
+        &self.edges
+    }
+
+    fn outgoing(&self, node_index: node::Index) -> OutgoingEdges<'_, E> {
+        OutgoingEdges::Linked { edges: &self.edges, next: self.nodes[node_index].first_outgoing_edge }
+    }
+
+    fn incoming(&self, node_index: node::Index) -> IncomingEdges<'_, E> {
+        IncomingEdges::new(self.nodes[node_index].first_incoming_edge, &self.edges)
+    }
+
+    fn indexed_nodes(&self, pairs: &[(String, String)]) -> Option<Vec<node::Index>> {
+        Graph::indexed_nodes(self, pairs)
+    }
+
+    fn indexed_node_bound(&self, pairs: &[(String, String)]) -> Option<usize> {
+        match self.attribute_index {
+            Some(ref attribute_index) if !pairs.is_empty() => Some(attribute_index.nodes_bound(pairs)),
+            _ => None,
+        }
+    }
+}
+
+pub struct Successors<'graph, N: 'graph, E: 'graph> {
+    graph: &'graph Graph<N, E>,
     current_edge_index: Option<edge::Index>,
 }
 
-impl<'graph> Iterator for Successors<'graph> {
+impl<'graph, N, E> Iterator for Successors<'graph, N, E> {
     type Item = node::Index;
 
     fn next(&mut self) -> Option<node::Index> {
@@ -94,7 +796,7 @@ mod tests {
     use super::*;
     #[test]
     fn create_small_graph() {
-        let mut graph = Graph { nodes: vec![], edges: vec![] };
+        let mut graph = Graph::new();
         let node0 = graph.add_node("node0".to_string(), None);
         let node1 = graph.add_node("node1".to_string(), None);
         graph.add_edge(node0, node1, "edge0".to_string(), None);
@@ -103,13 +805,13 @@ mod tests {
         assert_eq!(1, graph.edges.len());
         match graph.nodes[0].first_outgoing_edge {
             Some(index) => assert_eq!(1, graph.edges[index].target),
-            None => assert!(false),
+            None => panic!(),
         }
     }
 
     #[test]
     fn create_list_graph() {
-        let mut graph = Graph { nodes: vec![], edges: vec![] };
+        let mut graph = Graph::new();
         let node0 = graph.add_node("node0".to_string(), None);
         let node1 = graph.add_node("node1".to_string(), None);
         let node2 = graph.add_node("node2".to_string(), None);
@@ -127,25 +829,21 @@ mod tests {
 
     #[test]
     fn list_node_edges() {
-        let mut graph = Graph { nodes: vec![], edges: vec![] };
+        let mut graph = Graph::new();
         let node0 = graph.add_node("node0".to_string(), None);
         graph.add_edge(node0, node0, "edge0".to_string(), None);
         graph.add_edge(node0, node0, "edge1".to_string(), None);
         graph.add_edge(node0, node0, "edge2".to_string(), None);
         let mut targets = vec![];
-        let mut successors = graph.successors(0);
-        loop {
-            match successors.next() {
-                Some(edge) => targets.push(edge),
-                None => break,
-            }
+        for edge in graph.successors(0) {
+            targets.push(edge);
         }
         assert_eq!(vec![0,0,0], targets);
     }
 
     #[test]
     fn node_attributes() {
-        let mut graph = Graph { nodes: vec![], edges: vec![] };
+        let mut graph = Graph::new();
 
         let mut attributes = HashMap::new();
         attributes.insert("key".to_string(), "value".to_string());
@@ -155,16 +853,16 @@ mod tests {
             Some(ref attrs) => {
                 match attrs.get("key") {
                     Some(value) => assert_eq!(&"value".to_string(), value),
-                    None => assert!(false),
+                    None => panic!(),
                 }
             },
-            None => assert!(false),
+            None => panic!(),
         }
     }
 
     #[test]
     fn edge_attributes() {
-        let mut graph = Graph { nodes: vec![], edges: vec![] };
+        let mut graph = Graph::new();
 
         let mut attributes = HashMap::new();
         attributes.insert("key".to_string(), "value".to_string());
@@ -175,21 +873,347 @@ mod tests {
             Some(ref attrs) => {
                 match attrs.get("key") {
                     Some(value) => assert_eq!(&"value".to_string(), value),
-                    None => assert!(false),
+                    None => panic!(),
                 }
             },
-            None => assert!(false),
+            None => panic!(),
         }
     }
 
     #[test]
     fn node_edges() {
-        let mut graph = Graph { nodes: vec![], edges: vec![] };
+        let mut graph = Graph::new();
         let node0 = graph.add_node("node0".to_string(), None);
         graph.add_edge(node0, node0, "edge0".to_string(), None);
         graph.add_edge(node0, node0, "edge1".to_string(), None);
         graph.add_edge(node0, node0, "edge1".to_string(), None);
 
-        assert_eq!(vec![2,1,0], graph.edges_for_node(node0));
+        assert_eq!(vec![0,1,2], graph.edges_for_node(node0));
+    }
+
+    #[test]
+    fn edge_order_by_attribute() {
+        let position = |position: &str| {
+            let mut attributes = HashMap::new();
+            attributes.insert("position".to_string(), position.to_string());
+            Some(attributes)
+        };
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), None);
+        graph.add_edge(node0, node0, "edge0".to_string(), position("10"));
+        graph.add_edge(node0, node0, "edge1".to_string(), None);
+        graph.add_edge(node0, node0, "edge2".to_string(), position("9"));
+        graph.add_edge(node0, node0, "edge3".to_string(), position("9"));
+
+        graph.order_edges_by_attribute("position");
+        assert_eq!(vec![2, 3, 0, 1], graph.edges_for_node(node0));
+        graph.add_edge(node0, node0, "edge4".to_string(), position("9"));
+        graph.add_edge(node0, node0, "edge5".to_string(), position("1"));
+        assert_eq!(vec![5, 2, 3, 4, 0, 1], graph.edges_for_node(node0));
+        assert_eq!(vec![5, 2, 3, 4, 0, 1], graph.freeze().thaw().edges_for_node(node0));
+
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), None);
+        graph.add_edge(node0, node0, "edge0".to_string(), position("2"));
+        graph.add_edge(node0, node0, "edge1".to_string(), position("1"));
+        graph.order_edges_by_attribute("position");
+        assert_eq!(vec![1, 0], graph.edges_for_node(node0));
+        graph.order_edges_by_insertion();
+        assert_eq!(vec![0, 1], graph.edges_for_node(node0));
+
+        // integers before other values, whichever way they are placed
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), None);
+        for value in &["1a", "10", "2"] {
+            graph.add_edge(node0, node0, "edge".to_string(), position(value));
+        }
+        graph.order_edges_by_attribute("position");
+        assert_eq!(vec![2, 1, 0], graph.edges_for_node(node0));
+        graph.add_edge(node0, node0, "edge".to_string(), position("1b"));
+        graph.add_edge(node0, node0, "edge".to_string(), position("3"));
+        assert_eq!(vec![2, 4, 1, 0, 3], graph.edges_for_node(node0));
+        graph.order_edges_by_attribute("position");
+        assert_eq!(vec![2, 4, 1, 0, 3], graph.edges_for_node(node0));
+    }
+
+    #[test]
+    fn remove_edge() {
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), None);
+        graph.add_edge(node0, node0, "edge0".to_string(), None);
+        graph.add_edge(node0, node0, "edge1".to_string(), None);
+        graph.add_edge(node0, node0, "edge2".to_string(), None);
+
+        graph.remove_edge(1);
+        assert_eq!(vec![0,2], graph.edges_for_node(node0));
+        graph.remove_edge(2);
+        assert_eq!(vec![0], graph.edges_for_node(node0));
+        assert!(!graph.contains_edge(2));
+        assert_eq!(1, graph.edge_count());
+        assert_eq!(3, graph.edges.len());
+    }
+
+    #[test]
+    fn remove_node() {
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), None);
+        let node1 = graph.add_node("node1".to_string(), None);
+        let node2 = graph.add_node("node2".to_string(), None);
+        graph.add_edge(node0, node1, "edge0".to_string(), None);
+        graph.add_edge(node1, node2, "edge1".to_string(), None);
+        graph.add_edge(node0, node2, "edge2".to_string(), None);
+
+        graph.remove_node(node1);
+        assert!(!graph.contains_node(node1));
+        assert_eq!(vec![0, 2], graph.node_indices());
+        assert_eq!(vec![2], graph.edges_for_node(node0));
+        assert_eq!(1, graph.edge_count());
+
+        // later nodes never take over the index of a removed one
+        let node3 = graph.add_node("node3".to_string(), None);
+        assert_eq!(3, node3);
+    }
+
+    #[test]
+    fn incoming_edges() {
+        let mut graph = Graph::new();
+        for n in 0..4 {
+            graph.add_node(format!("node{}", n), None);
+        }
+        graph.add_edge(0, 3, "edge0".to_string(), None);
+        graph.add_edge(1, 2, "edge1".to_string(), None);
+        graph.add_edge(2, 3, "edge2".to_string(), None);
+        graph.add_edge(1, 3, "edge3".to_string(), None);
+        assert_eq!(vec![0, 2, 3], graph.incoming_edges_for_node(3));
+
+        graph.retarget_edge(1, 3);
+        assert_eq!(vec![0, 1, 2, 3], graph.incoming_edges_for_node(3));
+        assert!(graph.incoming_edges_for_node(2).is_empty());
+        graph.remove_edge(2);
+        assert_eq!(vec![0, 1, 3], graph.incoming_edges_for_node(3));
+
+        graph.remove_node(3);
+        assert_eq!(0, graph.edge_count());
+        assert!(graph.edges_for_node(1).is_empty());
+        assert_eq!(Err(Error::InvalidNode(3)), graph.try_incoming_edges_for_node(3));
+    }
+
+    #[test]
+    #[should_panic]
+    fn add_edge_to_removed_node() {
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), None);
+        let node1 = graph.add_node("node1".to_string(), None);
+        graph.remove_node(node1);
+        graph.add_edge(node0, node1, "edge0".to_string(), None);
+    }
+
+    #[test]
+    fn invalid_indexes() {
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), None);
+
+        assert_eq!(Err(Error::InvalidNode(1)), graph.try_add_edge(node0, 1, "edge0".to_string(), None));
+        assert_eq!(Err(Error::InvalidNode(5)), graph.try_edges_for_node(5));
+        assert!(graph.try_successors(5).is_err());
+        assert_eq!(Err(Error::InvalidEdge(0)), graph.try_remove_edge(0));
+        assert_eq!(Ok(0), graph.try_add_edge(node0, node0, "edge0".to_string(), None));
+        assert_eq!(Ok(()), graph.try_remove_node(node0));
+        assert_eq!(Err(Error::InvalidNode(0)), graph.try_remove_node(node0));
+    }
+
+    #[test]
+    fn identifier_index() {
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), None);
+        graph.index_identifiers(false).unwrap();
+        let node1 = graph.add_node("node1".to_string(), None);
+        let node2 = graph.add_node("node1".to_string(), None);
+
+        assert_eq!(Some(node0), graph.node_by_identifier("node0"));
+        assert_eq!(vec![node1, node2], graph.nodes_by_identifier("node1"));
+        assert_eq!(None, graph.node_by_identifier("node3"));
+
+        graph.remove_node(node1);
+        assert_eq!(Some(node2), graph.node_by_identifier("node1"));
+        graph.remove_node(node2);
+        assert_eq!(None, graph.node_by_identifier("node1"));
+    }
+
+    #[test]
+    fn identifier_lookup_without_index() {
+        let mut graph = Graph::new();
+        graph.add_node("node0".to_string(), None);
+        let node1 = graph.add_node("node1".to_string(), None);
+        assert_eq!(Some(node1), graph.node_by_identifier("node1"));
+    }
+
+    #[test]
+    fn unique_identifiers() {
+        let mut graph = Graph::new();
+        graph.add_node("node0".to_string(), None);
+        graph.add_node("node0".to_string(), None);
+        assert_eq!(Err(Error::DuplicateIdentifier("node0".to_string())), graph.index_identifiers(true));
+
+        let mut graph = Graph::new();
+        graph.index_identifiers(true).unwrap();
+        let node0 = graph.add_node("node0".to_string(), None);
+        assert_eq!(Err(Error::DuplicateIdentifier("node0".to_string())), graph.try_add_node("node0".to_string(), None));
+        assert_eq!(1, graph.nodes.len());
+
+        // the identifier is free again once its node is removed
+        graph.remove_node(node0);
+        assert_eq!(Ok(1), graph.try_add_node("node0".to_string(), None));
+    }
+
+    fn lemma(lemma: &str) -> Attributes {
+        let mut attributes = HashMap::new();
+        attributes.insert("lemma".to_string(), lemma.to_string());
+        attributes.insert("pos".to_string(), "NN".to_string());
+        Some(attributes)
+    }
+
+    #[test]
+    fn attribute_index() {
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), lemma("dog"));
+        graph.index_attributes();
+        let node1 = graph.add_node("node1".to_string(), lemma("cat"));
+        let node2 = graph.add_node("node2".to_string(), lemma("dog"));
+        let edge0 = graph.add_edge(node0, node1, "edge0".to_string(), None);
+        graph.add_edge(node1, node2, "edge1".to_string(), None);
+
+        assert_eq!(vec![0, 2], graph.nodes_by_attribute("lemma", "dog"));
+        assert_eq!(vec![0, 1, 2], graph.nodes_by_attribute("pos", "NN"));
+        assert_eq!(vec![edge0], graph.edges_by_identifier("edge0"));
+        let pairs = vec![("lemma".to_string(), "cat".to_string()), ("pos".to_string(), "NN".to_string())];
+        assert_eq!(Some(vec![1]), graph.indexed_nodes(&pairs));
+        assert_eq!(None, graph.indexed_nodes(&[]));
+
+        graph.remove_node(node1);
+        assert_eq!(Vec::<usize>::new(), graph.nodes_by_attribute("lemma", "cat"));
+        assert_eq!(Vec::<usize>::new(), graph.edges_by_identifier("edge0"));
+        assert_eq!(vec![0, 2], graph.nodes_by_attribute("pos", "NN"));
+    }
+
+    #[test]
+    fn attribute_lookup_without_index() {
+        let mut graph = Graph::new();
+        graph.add_node("node0".to_string(), lemma("dog"));
+        graph.add_node("node1".to_string(), lemma("cat"));
+        graph.add_edge(0, 1, "edge0".to_string(), None);
+        assert_eq!(vec![1], graph.nodes_by_attribute("lemma", "cat"));
+        assert_eq!(vec![0], graph.edges_by_identifier("edge0"));
+        assert_eq!(None, graph.indexed_nodes(&[("lemma".to_string(), "cat".to_string())]));
+    }
+
+    #[test]
+    fn interned_identifiers() {
+        let mut graph = Graph::new();
+        graph.add_node("node0".to_string(), None);
+        graph.add_node("node1", None);
+        graph.add_edge(0, 1, "dobj", None);
+        graph.index_identifiers(false).unwrap();
+        let node1 = graph.intern("node1");
+        assert!(!Symbol::ptr_eq(&graph.nodes[1].identifier, &node1));
+
+        graph.intern_identifiers();
+        graph.add_node("node1", None);
+        graph.add_edge(1, 2, "dobj", None);
+        assert!(Symbol::ptr_eq(&graph.nodes[1].identifier, &graph.nodes[2].identifier));
+        assert!(Symbol::ptr_eq(&graph.edges[0].identifier, &graph.edges[1].identifier));
+        assert_eq!(vec![1, 2], graph.nodes_by_identifier("node1"));
+        assert_eq!(3, graph.symbol_table().unwrap().len());
+
+        let mut attributes = HashMap::new();
+        attributes.insert("lemma".to_string(), "node1".to_string());
+        let interned = graph.intern_attributes(&attributes);
+        assert!(Symbol::ptr_eq(interned.get("lemma").unwrap(), &graph.nodes[1].identifier));
+    }
+
+    #[test]
+    fn interned_attribute_mutation() {
+        let mut graph: Graph<Option<InternedAttributes>, Option<InternedAttributes>> = Graph::default();
+        let mut attributes = HashMap::new();
+        attributes.insert("lemma".to_string(), "dog".to_string());
+        let interned = graph.intern_attributes(&attributes);
+        graph.add_node("node0", Some(interned));
+        graph.add_node("node1", None);
+        graph.add_edge(0, 1, "edge0", None);
+
+        assert_eq!(Some("dog".to_string()), graph.set_node_attribute(0, "lemma", "cat"));
+        graph.set_node_attribute(1, "lemma", "cat");
+        graph.set_edge_attribute(0, "relation", "lemma");
+        let lemma = |attributes: &Option<InternedAttributes>| attributes.as_ref().unwrap().get("lemma").unwrap().clone();
+        assert!(Symbol::ptr_eq(&lemma(&graph.nodes[0].attributes), &lemma(&graph.nodes[1].attributes)));
+        assert!(Symbol::ptr_eq(&graph.intern("lemma"), graph.edges[0].attributes.as_ref().unwrap().get("relation").unwrap()));
+        // lemma, dog, cat and relation, and the identifiers interned once the
+        // graph had a table
+        assert_eq!(7, graph.symbol_table().unwrap().len());
+    }
+
+    #[test]
+    fn attribute_mutation() {
+        let mut graph = Graph::new();
+        graph.add_node("node0".to_string(), lemma("dog"));
+        graph.add_node("node1".to_string(), None);
+        graph.add_node("node2".to_string(), lemma("dog"));
+        graph.add_edge(0, 1, "edge0".to_string(), None);
+        graph.add_edge(0, 2, "edge1".to_string(), None);
+        graph.index_attributes();
+
+        assert_eq!(None, graph.set_node_attribute(1, "lemma", "dog"));
+        assert_eq!(vec![0, 1, 2], graph.nodes_by_attribute("lemma", "dog"));
+        assert_eq!(Some("dog".to_string()), graph.set_node_attribute(0, "lemma", "cat"));
+        assert_eq!(vec![1, 2], graph.nodes_by_attribute("lemma", "dog"));
+        assert_eq!(vec![0], graph.nodes_by_attribute("lemma", "cat"));
+        assert_eq!(Some("dog".to_string()), graph.remove_node_attribute(2, "lemma"));
+        assert_eq!(None, graph.remove_node_attribute(2, "lemma"));
+        assert_eq!(Some(vec![1]), graph.indexed_nodes(&[("lemma".to_string(), "dog".to_string())]));
+
+        graph.order_edges_by_attribute("position");
+        graph.set_edge_attribute(0, "position", "2");
+        graph.set_edge_attribute(1, "position", "1");
+        assert_eq!(vec![1, 0], graph.edges_for_node(0));
+        assert_eq!(Some("1".to_string()), graph.remove_edge_attribute(1, "position"));
+        assert_eq!(vec![0, 1], graph.edges_for_node(0));
+
+        graph.retarget_edge(1, 1);
+        assert_eq!(vec![1, 1], graph.successors(0).collect::<Vec<_>>());
+        assert_eq!(Err(Error::InvalidNode(3)), graph.try_retarget_edge(1, 3));
+        graph.remove_node(2);
+        assert_eq!(Err(Error::InvalidNode(2)), graph.try_set_node_attribute(2, "lemma", "dog"));
+        assert_eq!(Err(Error::InvalidEdge(2)), graph.try_set_edge_attribute(2, "position", "3"));
+    }
+
+    #[test]
+    fn identifier_mutation() {
+        let mut graph = Graph::new();
+        graph.add_node("node0".to_string(), None);
+        graph.add_node("node1".to_string(), None);
+        graph.add_node("node2".to_string(), None);
+        graph.add_edge(0, 1, "edge0".to_string(), None);
+        graph.index_identifiers(true).unwrap();
+        graph.index_attributes();
+
+        graph.set_node_identifier(2, "head");
+        assert_eq!(Some(2), graph.node_by_identifier("head"));
+        assert_eq!(None, graph.node_by_identifier("node2"));
+        assert_eq!(Err(Error::DuplicateIdentifier("node0".to_string())), graph.try_set_node_identifier(1, "node0"));
+        assert_eq!("node1", graph.nodes[1].identifier);
+        graph.set_node_identifier(1, "node1");
+
+        let mut graph_with_duplicates = Graph::new();
+        graph_with_duplicates.add_node("node0".to_string(), None);
+        graph_with_duplicates.add_node("node1".to_string(), None);
+        graph_with_duplicates.index_identifiers(false).unwrap();
+        graph_with_duplicates.set_node_identifier(1, "node0");
+        graph_with_duplicates.set_node_identifier(0, "node1");
+        graph_with_duplicates.set_node_identifier(0, "node0");
+        assert_eq!(vec![0, 1], graph_with_duplicates.nodes_by_identifier("node0"));
+
+        graph.set_edge_identifier(0, "dobj");
+        assert_eq!(vec![0], graph.edges_by_identifier("dobj"));
+        assert!(graph.edges_by_identifier("edge0").is_empty());
     }
 }
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use node;
+use edge;
+
+// Inverted indexes from node attributes and edge identifiers to the nodes and
+// edges carrying them. Index lists are kept in ascending order.
+pub struct AttributeIndex<N> {
+    pairs: fn(&N) -> Vec<(String, String)>,
+    nodes: HashMap<String, HashMap<String, Vec<node::Index>>>,
+    edges: HashMap<String, Vec<edge::Index>>,
+}
+
+impl<N> AttributeIndex<N> {
+    pub fn new(pairs: fn(&N) -> Vec<(String, String)>) -> AttributeIndex<N> {
+        AttributeIndex { pairs, nodes: HashMap::new(), edges: HashMap::new() }
+    }
+
+    pub fn insert_node(&mut self, node_index: node::Index, attributes: &N) {
+        for (key, value) in (self.pairs)(attributes) {
+            insert_sorted(self.nodes.entry(key).or_default().entry(value).or_default(), node_index);
+        }
+    }
+
+    pub fn remove_node(&mut self, node_index: node::Index, attributes: &N) {
+        for (key, value) in (self.pairs)(attributes) {
+            let key_now_empty = match self.nodes.get_mut(&key) {
+                Some(values) => {
+                    let value_now_empty = match values.get_mut(&value) {
+                        Some(indexes) => {
+                            indexes.retain(|&index| index != node_index);
+                            indexes.is_empty()
+                        }
+                        None => false,
+                    };
+                    if value_now_empty {
+                        values.remove(&value);
+                    }
+                    values.is_empty()
+                }
+                None => false,
+            };
+            if key_now_empty {
+                self.nodes.remove(&key);
+            }
+        }
+    }
+
+    pub fn insert_edge(&mut self, edge_index: edge::Index, identifier: &str) {
+        insert_sorted(self.edges.entry(identifier.to_string()).or_default(), edge_index);
+    }
+
+    pub fn remove_edge(&mut self, edge_index: edge::Index, identifier: &str) {
+        let now_empty = match self.edges.get_mut(identifier) {
+            Some(indexes) => {
+                indexes.retain(|&index| index != edge_index);
+                indexes.is_empty()
+            }
+            None => false,
+        };
+        if now_empty {
+            self.edges.remove(identifier);
+        }
+    }
+
+    pub fn nodes(&self, key: &str, value: &str) -> &[node::Index] {
+        match self.nodes.get(key).and_then(|values| values.get(value)) {
+            Some(indexes) => indexes,
+            None => &[],
+        }
+    }
+
+    pub fn edges(&self, identifier: &str) -> &[edge::Index] {
+        match self.edges.get(identifier) {
+            Some(indexes) => indexes,
+            None => &[],
+        }
+    }
+
+    // The length of the shortest of the pairs' lists, see GraphView::indexed_node_bound.
+    pub fn nodes_bound(&self, pairs: &[(String, String)]) -> usize {
+        pairs.iter().map(|(key, value)| self.nodes(key, value).len()).min().unwrap_or(0)
+    }
+
+    // The nodes having every one of the pairs, in ascending order. The lists
+    // are intersected starting from the shortest.
+    pub fn nodes_with_all(&self, pairs: &[(String, String)]) -> Vec<node::Index> {
+        let mut lists: Vec<&[node::Index]> = pairs.iter().map(|(key, value)| self.nodes(key, value)).collect();
+        lists.sort_by_key(|list| list.len());
+        let mut lists = lists.into_iter();
+        let mut candidates = match lists.next() {
+            Some(list) => list.to_vec(),
+            None => return vec![],
+        };
+        for list in lists {
+            candidates.retain(|node_index| list.binary_search(node_index).is_ok());
+        }
+        candidates
+    }
+}
+
+// Entries are mostly added with a new highest index, when nodes and edges are
+// added, and otherwise when existing ones change.
+pub fn insert_sorted(indexes: &mut Vec<usize>, index: usize) {
+    match indexes.last() {
+        Some(&last) if last > index => {
+            if let Err(position) = indexes.binary_search(&index) {
+                indexes.insert(position, index);
+            }
+        }
+        Some(&last) if last == index => {}
+        _ => indexes.push(index),
+    }
+}
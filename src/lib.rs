@@ -1,48 +1,141 @@
+#[cfg(feature = "rayon")]
+extern crate rayon;
+
 pub mod graph;
 mod node;
 mod edge;
+mod error;
+mod index;
 
 pub mod matching;
+pub mod snapshot;
+pub mod expansion;
+pub mod subgraph;
+pub mod traversal;
+pub mod paths;
+pub mod tree;
+pub mod cycles;
+pub mod components;
+pub mod corpus;
+pub mod planner;
+pub mod prepared;
+pub mod view;
+pub mod frozen;
+pub mod symbols;
+pub mod rewrite;
+pub mod diff;
+pub mod canonical;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+
+pub use error::Error;
+use error::unwrap;
 
-pub fn match_graph(
-    query: &graph::Graph, query_root_index: node::Index, graph: &graph::Graph, graph_root_index: Option<node::Index>, equality: &matching::EqualityRequirement)
-    -> Vec<matching::MatchedComponents> {
+pub fn match_graph<QN, QE, G>(
+    query: &graph::Graph<QN, QE>, query_root_index: node::Index, graph: &G, graph_root_index: Option<node::Index>, equality: &matching::EqualityRequirement)
+    -> Vec<matching::MatchedComponents>
+    where G: view::GraphView, QN: matching::Matches<G::N>, QE: matching::Matches<G::E> {
+        unwrap(try_match_graph(query, query_root_index, graph, graph_root_index, equality))
+    }
+
+pub fn try_match_graph<QN, QE, G>(
+    query: &graph::Graph<QN, QE>, query_root_index: node::Index, graph: &G, graph_root_index: Option<node::Index>, equality: &matching::EqualityRequirement)
+    -> Result<Vec<matching::MatchedComponents>, Error>
+    where G: view::GraphView, QN: matching::Matches<G::N>, QE: matching::Matches<G::E> {
+        prepared::PreparedQuery::try_new(query, query_root_index, equality)?.try_execute(graph, graph_root_index)
+    }
+
+fn check_query<QN, QE>(query: &graph::Graph<QN, QE>, query_root_index: node::Index) -> Result<(), Error> {
+    if !query.contains_node(query_root_index) {
+        return Err(Error::InvalidQueryRoot(query_root_index));
+    }
+    // matching recurses along the query's edges and would never finish on a
+    // cyclic query, cycles in the graph being searched are harmless
+    query.check_acyclic()
+}
+
+fn candidate_roots<QN, QE, G>(
+    query: &graph::Graph<QN, QE>, query_root_index: node::Index, graph: &G, graph_root_index: Option<node::Index>, equality: &matching::EqualityRequirement)
+    -> Result<Vec<node::Index>, Error>
+    where G: view::GraphView, QN: matching::Matches<G::N> {
         let query_root_node = &query.nodes[query_root_index];
 
         let mut graph_roots: Vec<usize> = Vec::new();
         match graph_root_index {
             Some(index) => {
+                graph.check_node(index)?;
                 graph_roots.push(index);
             },
             None => {
-                for i in 0..graph.nodes.len() {
-                    if query_root_node.matches(&graph.nodes[i], &equality) {
+                // with an attribute index only the nodes having every attribute
+                // the query root requires need to be tried
+                let required = query_root_node.attributes.required_attributes();
+                let candidates = match *equality {
+                    matching::EqualityRequirement::Complete => graph.indexed_nodes(&required),
+                    _ => None,
+                };
+                for i in candidates.unwrap_or_else(|| graph.node_indices()) {
+                    if query_root_node.matches(&graph.nodes()[i], equality) {
                         graph_roots.push(i);
                     }
                 }
             }
         }
+        Ok(graph_roots)
+    }
 
-        return graph_roots.iter()
-            .map(|&root_index| matching::recusive_node_match(query_root_index, root_index, &query, &graph, None, &equality))
-            .filter(|&ref matched_components| matched_components.list.len() == query.nodes.len())
-            .collect::<Vec<_>>();
+// Runs a query against every graph in a corpus, pairing each match with the id
+// of the graph it was found in.
+pub fn match_corpus<QN, QE, N, E>(
+    query: &graph::Graph<QN, QE>, query_root_index: node::Index, corpus: &corpus::Corpus<N, E>, equality: &matching::EqualityRequirement)
+    -> Vec<(corpus::GraphId, matching::MatchedComponents)>
+    where QN: matching::Matches<N>, QE: matching::Matches<E> {
+        unwrap(try_match_corpus(query, query_root_index, corpus, equality))
     }
 
-pub fn expand_subgraph(
-    graph: &graph::Graph, root_index: node::Index, banned_identifiers: &Vec<String>)
+pub fn try_match_corpus<QN, QE, N, E>(
+    query: &graph::Graph<QN, QE>, query_root_index: node::Index, corpus: &corpus::Corpus<N, E>, equality: &matching::EqualityRequirement)
+    -> Result<Vec<(corpus::GraphId, matching::MatchedComponents)>, Error>
+    where QN: matching::Matches<N>, QE: matching::Matches<E> {
+        Ok(corpus::CorpusMatches::try_new(query, query_root_index, corpus.graphs.iter(), equality)?.collect())
+    }
+
+pub fn expand_subgraph<N, E>(
+    graph: &graph::Graph<N, E>, root_index: node::Index, banned_identifiers: &Vec<String>)
     -> Vec<node::Index> {
+        unwrap(try_expand_subgraph(graph, root_index, banned_identifiers))
+    }
+
+pub fn try_expand_subgraph<N, E>(
+    graph: &graph::Graph<N, E>, root_index: node::Index, banned_identifiers: &Vec<String>)
+    -> Result<Vec<node::Index>, Error> {
         let mut node_list = vec![root_index];
-        let root_node_edges: Vec<node::Index> = graph.edges_for_node(root_index);
+        let root_node_edges: Vec<node::Index> = graph.try_edges_for_node(root_index)?;
         for edge in root_node_edges {
-            if banned_identifiers.contains(&graph.edges[edge].identifier) {
+            if banned_identifiers.iter().any(|identifier| *identifier == graph.edges[edge].identifier) {
                 continue;
             }
             let target = graph.edges[edge].target;
-            for inner_node in expand_subgraph(graph, target, &banned_identifiers) {
+            for inner_node in try_expand_subgraph(graph, target, banned_identifiers)? {
                 node_list.push(inner_node);
             }
         }
 
-        return node_list;
+        Ok(node_list)
+    }
+
+pub fn expand_subgraph_with<N, E>(
+    graph: &graph::Graph<N, E>, root_index: node::Index, options: &expansion::ExpandOptions<N, E>)
+    -> Result<Vec<node::Index>, Error> {
+        expansion::expand(graph, root_index, options)
+    }
+
+pub fn extract_subgraph<N: Clone, E: Clone>(
+    graph: &graph::Graph<N, E>, root_index: node::Index, options: &expansion::ExpandOptions<N, E>, edges: subgraph::EdgeSelection)
+    -> Result<subgraph::Subgraph<N, E>, Error> {
+        let (node_list, edge_list) = expansion::expand_with_edges(graph, root_index, options)?;
+        match edges {
+            subgraph::EdgeSelection::Traversed => subgraph::build(graph, &node_list, &edge_list),
+            subgraph::EdgeSelection::Induced => subgraph::induced(graph, &node_list),
+        }
     }
@@ -1,6 +1,13 @@
+use std::collections::HashMap;
 use graph;
-use edge;
 use node;
+use edge;
+use view::GraphView;
+use prepared::PreparedQuery;
+use symbols::SymbolTable;
+
+// The payload carried by nodes and edges unless a graph is given its own types.
+pub type Attributes = Option<HashMap<String, String>>;
 
 #[derive(Debug,PartialEq)]
 pub struct Component {
@@ -22,69 +29,242 @@ pub enum EqualityRequirement {
                * - IgnoreCase */
 }
 
-pub fn values_match(value1: &String, value2: &String, equality: &EqualityRequirement) -> bool {
-    match *equality {
-        EqualityRequirement::Complete => {
-            return value1 == value2;
-        },
-        EqualityRequirement::Contains => {
-            return value1.contains(value2);
-        },
+// Decides whether a query payload (self) accepts the payload of a graph node or
+// edge. Implement this for custom node and edge types to control matching.
+pub trait Matches<Other: ?Sized = Self> {
+    fn matches(&self, other: &Other, equality: &EqualityRequirement) -> bool;
+
+    // Key value pairs that every payload matched under Complete equality must
+    // have, used to look up candidate nodes in a graph's attribute index. The
+    // default of none means every node is a candidate.
+    fn required_attributes(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    // Matches as matches does, given the requirements prepared::PreparedQuery
+    // gathered from this payload's required_attributes. Payloads fully
+    // described by those pairs check the sorted list instead of themselves, by
+    // default the payload is matched as usual.
+    fn matches_compiled(&self, _requirements: &Requirements, other: &Other, equality: &EqualityRequirement) -> bool {
+        self.matches(other, equality)
+    }
+
+    // Whether every payload of the other type holds at most one value for a
+    // key, so that required pairs sharing a key but not a value are never met
+    // by one payload under Complete equality. prepared::PreparedQuery prunes
+    // graph nodes by their number of outgoing edges only for such payloads;
+    // the default of false is always safe.
+    fn single_valued() -> bool where Self: Sized {
+        false
     }
 }
 
-pub fn recusive_node_match(query_root_index: usize,
-                           graph_root_index: usize,
-                           query: &graph::Graph,
-                           graph: &graph::Graph,
-                           source_edge: Option<edge::Index>,
-                           equality: &EqualityRequirement)
-                           -> MatchedComponents {
-    if !query.nodes[query_root_index].matches(&graph.nodes[graph_root_index], &equality) {
-        return MatchedComponents { list: vec![] };
+// A query payload's required attribute pairs, sorted by key, gathered once when
+// a query is prepared.
+pub struct Requirements {
+    pairs: Vec<(String, String)>,
+}
+
+impl Requirements {
+    pub fn new(mut pairs: Vec<(String, String)>) -> Requirements {
+        pairs.sort();
+        Requirements { pairs }
     }
 
-    let mut matched_components = MatchedComponents {
-        list: vec![Component {
-                       from_edge: source_edge,
-                       node: graph_root_index,
-                   }],
-    };
+    pub fn pairs(&self) -> &[(String, String)] {
+        &self.pairs
+    }
+}
+
+// Payloads that can be listed as key value pairs for Graph::index_attributes.
+pub trait AttributePairs {
+    fn attribute_pairs(&self) -> Vec<(String, String)>;
 
-    if query.edges_for_node(query_root_index).len() == 0 {
-        return matched_components;
+    // The value of one attribute, found among the pairs unless a payload can
+    // look it up directly.
+    fn attribute(&self, key: &str) -> Option<String> {
+        self.attribute_pairs().into_iter().find(|(candidate, _)| candidate == key).map(|(_, value)| value)
     }
+}
 
-    for query_edge_index in query.edges_for_node(query_root_index) {
-        let mut matching_edge_in_graph: Option<edge::Index> = None;
-        for graph_edge_index in graph.edges_for_node(graph_root_index) {
-            if query.edges[query_edge_index].matches(&graph.edges[graph_edge_index], &equality) {
-                matching_edge_in_graph = Some(graph_edge_index);
-                break;
-            }
+// Payloads whose attributes can be changed through Graph::set_node_attribute
+// and the like. Both methods return the value the key had before.
+pub trait AttributesMut {
+    fn set_attribute(&mut self, key: &str, value: &str) -> Option<String>;
+    fn remove_attribute(&mut self, key: &str) -> Option<String>;
+
+    // set_attribute for payloads holding symbols, which intern the key and
+    // value in the table given. Graphs keeping a symbol table set attributes
+    // this way. Other payloads have no use for the table.
+    fn set_attribute_interned(&mut self, key: &str, value: &str, _symbols: &mut SymbolTable) -> Option<String> {
+        self.set_attribute(key, value)
+    }
+}
+
+impl AttributesMut for HashMap<String, String> {
+    fn set_attribute(&mut self, key: &str, value: &str) -> Option<String> {
+        self.insert(key.to_string(), value.to_string())
+    }
+
+    fn remove_attribute(&mut self, key: &str) -> Option<String> {
+        self.remove(key)
+    }
+}
+
+// a payload of None gains attributes when the first one is set
+impl<T: AttributesMut + Default> AttributesMut for Option<T> {
+    fn set_attribute(&mut self, key: &str, value: &str) -> Option<String> {
+        self.get_or_insert_with(T::default).set_attribute(key, value)
+    }
+
+    fn set_attribute_interned(&mut self, key: &str, value: &str, symbols: &mut SymbolTable) -> Option<String> {
+        self.get_or_insert_with(T::default).set_attribute_interned(key, value, symbols)
+    }
+
+    fn remove_attribute(&mut self, key: &str) -> Option<String> {
+        match *self {
+            Some(ref mut attributes) => attributes.remove_attribute(key),
+            None => None,
         }
-        match matching_edge_in_graph {
-            Some(edge) => {
-                match query.nodes[query.edges[query_edge_index].target]
-                    .matches(&graph.nodes[graph.edges[edge].target], &equality) {
-                    true => {
-                        let descendents =
-                            recusive_node_match(query.edges[query_edge_index].target,
-                                                graph.edges[matching_edge_in_graph.unwrap()]
-                                                    .target,
-                                                &query,
-                                                &graph,
-                                                Some(edge),
-                                                &equality);
-                        for descendent in descendents.list {
-                            matched_components.list.push(descendent);
-                        }
+    }
+}
+
+impl AttributePairs for HashMap<String, String> {
+    fn attribute_pairs(&self) -> Vec<(String, String)> {
+        self.iter().map(|(key, value)| (key.clone(), value.clone())).collect()
+    }
+
+    fn attribute(&self, key: &str) -> Option<String> {
+        self.get(key).cloned()
+    }
+}
+
+impl<T: AttributePairs> AttributePairs for Option<T> {
+    fn attribute_pairs(&self) -> Vec<(String, String)> {
+        match *self {
+            Some(ref attributes) => attributes.attribute_pairs(),
+            None => vec![],
+        }
+    }
+
+    fn attribute(&self, key: &str) -> Option<String> {
+        self.as_ref().and_then(|attributes| attributes.attribute(key))
+    }
+}
+
+// every query attribute must be present on the other side with a matching value
+impl Matches for HashMap<String, String> {
+    fn matches(&self, other: &HashMap<String, String>, equality: &EqualityRequirement) -> bool {
+        for pair in self {
+            match other.get(pair.0) {
+                Some(value) => {
+                    if !value_matches(pair.1, value, equality) {
+                        return false;
                     }
-                    false => return MatchedComponents { list: vec![] },
+                }
+                None => return false,
+            }
+        }
+        true
+    }
+
+    fn required_attributes(&self) -> Vec<(String, String)> {
+        self.attribute_pairs()
+    }
+
+    fn matches_compiled(&self, requirements: &Requirements, other: &HashMap<String, String>, equality: &EqualityRequirement) -> bool {
+        requirements.pairs().iter().all(|(key, value)| {
+            match other.get(key) {
+                Some(other_value) => value_matches(value, other_value, equality),
+                None => false,
+            }
+        })
+    }
+
+    fn single_valued() -> bool {
+        true
+    }
+}
+
+impl<T, U> Matches<Option<U>> for Option<T> where T: Matches<U> {
+    fn matches(&self, other: &Option<U>, equality: &EqualityRequirement) -> bool {
+        match *self {
+            Some(ref query) => {
+                match *other {
+                    Some(ref value) => query.matches(value, equality),
+                    // when the other side is empty and the query is not then it is not matched
+                    None => false,
                 }
             }
-            None => return MatchedComponents { list: vec![] },
+            // no payload means a blank query
+            None => true,
         }
     }
-    return matched_components;
+
+    fn required_attributes(&self) -> Vec<(String, String)> {
+        match *self {
+            Some(ref query) => query.required_attributes(),
+            None => vec![],
+        }
+    }
+
+    fn matches_compiled(&self, requirements: &Requirements, other: &Option<U>, equality: &EqualityRequirement) -> bool {
+        match *self {
+            Some(ref query) => {
+                match *other {
+                    Some(ref value) => query.matches_compiled(requirements, value, equality),
+                    None => false,
+                }
+            }
+            None => true,
+        }
+    }
+
+    fn single_valued() -> bool {
+        T::single_valued()
+    }
+}
+
+// graphs without payloads match on structure alone
+impl Matches for () {
+    fn matches(&self, _other: &(), _equality: &EqualityRequirement) -> bool {
+        true
+    }
+}
+
+// value_matches with the graph's value first, kept as it was for callers
+#[allow(clippy::ptr_arg)]
+pub fn values_match(value1: &String, value2: &String, equality: &EqualityRequirement) -> bool {
+    value_matches(value2, value1, equality)
+}
+
+// Whether a graph's attribute value satisfies a query's, the comparison every
+// Matches implementation for attribute maps makes.
+pub fn value_matches(query: &str, value: &str, equality: &EqualityRequirement) -> bool {
+    match *equality {
+        EqualityRequirement::Complete => {
+            value == query
+        },
+        EqualityRequirement::Contains => {
+            value.contains(query)
+        },
+    }
+}
+
+// Matches the query from query_root_index with that node at graph_root_index,
+// through the matcher prepared::PreparedQuery runs. Each query edge takes the
+// first graph edge matching it, and the components under a node that fails
+// to match are dropped.
+pub fn recusive_node_match<QN, QE, G>(query_root_index: usize,
+                                     graph_root_index: usize,
+                                     query: &graph::Graph<QN, QE>,
+                                     graph: &G,
+                                     source_edge: Option<edge::Index>,
+                                     equality: &EqualityRequirement)
+                                     -> MatchedComponents
+    where G: GraphView, QN: Matches<G::N>, QE: Matches<G::E> {
+    let prepared: PreparedQuery<QN, QE, G::N, G::E> = PreparedQuery::compile(query, query_root_index, equality);
+    let mut list = vec![];
+    prepared.match_from(query_root_index, graph, graph_root_index, source_edge, &mut list);
+    MatchedComponents { list }
 }
@@ -1,45 +1,23 @@
-use std::collections::HashMap;
 use edge;
-use matching;
-use matching::EqualityRequirement;
+use matching::{Attributes, EqualityRequirement, Matches};
+use symbols::Symbol;
 
 pub type Index = usize;
 
-pub struct Node {
-    pub identifier: String,
-    pub attributes: Option<HashMap<String, String>>,
+#[derive(Debug,PartialEq,Eq)]
+pub struct Node<N = Attributes> {
+    pub identifier: Symbol,
+    pub attributes: N,
     pub first_outgoing_edge: Option<edge::Index>,
+    pub first_incoming_edge: Option<edge::Index>,
+    pub removed: bool,
 }
 
-impl Node {
-    pub fn matches(&self, node: &Node, equality: &EqualityRequirement) -> bool {
-        // test the attributes
-        match self.attributes {
-            Some(ref attrs) => {
-                // test the node's attributes
-                match node.attributes {
-                    Some(ref node_attrs) => {
-                        // for each of the query attributes, test the node
-                        for pair in attrs {
-                            match node_attrs.get(pair.0) {
-                                Some(value) => {
-                                    if !matching::values_match(value, pair.1, &equality) {
-                                        return false;
-                                    }
-                                }
-                                None => return false,
-                            }
-                        }
-                    }
-                    // when node is empty and query is not then the node is not matched
-                    None => return false,
-                }
-            }
-            // no attributes means a blank query
-            None => return true,
+impl<N> Node<N> {
+    pub fn matches<M>(&self, node: &Node<M>, equality: &EqualityRequirement) -> bool
+        where N: Matches<M> {
+            self.attributes.matches(&node.attributes, equality)
         }
-        return true;
-    }
 }
 
 #[cfg(test)]
@@ -55,22 +33,28 @@ mod tests {
         attributes2.insert("core".to_string(), "worth".to_string());
 
         let node0 = Node {
-            identifier: "nodeid".to_string(),
+            identifier: "nodeid".into(),
             attributes: Some(attributes.clone()),
             first_outgoing_edge: None,
+            first_incoming_edge: None,
+            removed: false,
         };
         let node1 = Node {
-            identifier: "nodeid".to_string(),
+            identifier: "nodeid".into(),
             attributes: Some(attributes.clone()),
             first_outgoing_edge: None,
+            first_incoming_edge: None,
+            removed: false,
         };
         let node2 = Node {
-            identifier: "nodeid".to_string(),
+            identifier: "nodeid".into(),
             attributes: Some(attributes2.clone()),
             first_outgoing_edge: None,
+            first_incoming_edge: None,
+            removed: false,
         };
         assert!(node0.matches(&node1, &EqualityRequirement::Complete));
-        assert_eq!(false, node0.matches(&node2, &EqualityRequirement::Complete));
+        assert!(!node0.matches(&node2, &EqualityRequirement::Complete));
     }
 
     #[test]
@@ -81,14 +65,18 @@ mod tests {
         attributes2.insert("key".to_string(), "the values".to_string());
 
         let node0 = Node {
-            identifier: "nodeid".to_string(),
+            identifier: "nodeid".into(),
             attributes: Some(attributes.clone()),
             first_outgoing_edge: None,
+            first_incoming_edge: None,
+            removed: false,
         };
         let node1 = Node {
-            identifier: "nodeid".to_string(),
+            identifier: "nodeid".into(),
             attributes: Some(attributes2.clone()),
             first_outgoing_edge: None,
+            first_incoming_edge: None,
+            removed: false,
         };
         assert!(node0.matches(&node1, &EqualityRequirement::Contains));
     }
@@ -0,0 +1,120 @@
+use rayon::prelude::*;
+use graph;
+use node;
+use corpus;
+use error::{Error, unwrap};
+use matching::{EqualityRequirement, Matches, MatchedComponents};
+use prepared::PreparedQuery;
+use view::GraphView;
+
+// Parallel versions of the crate level matching functions, available with the
+// rayon feature. Candidate roots, and the graphs of a corpus, are searched on
+// the rayon thread pool but results come back in the same order as the serial
+// functions return them.
+
+pub fn match_graph<QN, QE, G>(
+    query: &graph::Graph<QN, QE>, query_root_index: node::Index, graph: &G, graph_root_index: Option<node::Index>, equality: &EqualityRequirement)
+    -> Vec<MatchedComponents>
+    where G: GraphView + Sync, QN: Matches<G::N> + Sync, QE: Matches<G::E> + Sync {
+        unwrap(try_match_graph(query, query_root_index, graph, graph_root_index, equality))
+    }
+
+pub fn try_match_graph<QN, QE, G>(
+    query: &graph::Graph<QN, QE>, query_root_index: node::Index, graph: &G, graph_root_index: Option<node::Index>, equality: &EqualityRequirement)
+    -> Result<Vec<MatchedComponents>, Error>
+    where G: GraphView + Sync, QN: Matches<G::N> + Sync, QE: Matches<G::E> + Sync {
+        let prepared = PreparedQuery::try_new(query, query_root_index, equality)?;
+        match_prepared(&prepared, graph, graph_root_index)
+    }
+
+fn match_prepared<QN, QE, G>(prepared: &PreparedQuery<QN, QE, G::N, G::E>, graph: &G, graph_root_index: Option<node::Index>)
+    -> Result<Vec<MatchedComponents>, Error>
+    where G: GraphView + Sync, QN: Matches<G::N> + Sync, QE: Matches<G::E> + Sync {
+        let graph_roots = prepared.candidate_roots(graph, graph_root_index)?;
+        // collecting an indexed parallel iterator keeps the order of the roots
+        let matches: Vec<Option<MatchedComponents>> = graph_roots.par_iter()
+            .map(|&root_index| prepared.match_root(graph, root_index))
+            .collect();
+        Ok(matches.into_iter().flatten().collect())
+    }
+
+pub fn match_corpus<QN, QE, N, E>(
+    query: &graph::Graph<QN, QE>, query_root_index: node::Index, corpus: &corpus::Corpus<N, E>, equality: &EqualityRequirement)
+    -> Vec<(corpus::GraphId, MatchedComponents)>
+    where QN: Matches<N> + Sync, QE: Matches<E> + Sync, N: Sync, E: Sync {
+        unwrap(try_match_corpus(query, query_root_index, corpus, equality))
+    }
+
+pub fn try_match_corpus<QN, QE, N, E>(
+    query: &graph::Graph<QN, QE>, query_root_index: node::Index, corpus: &corpus::Corpus<N, E>, equality: &EqualityRequirement)
+    -> Result<Vec<(corpus::GraphId, MatchedComponents)>, Error>
+    where QN: Matches<N> + Sync, QE: Matches<E> + Sync, N: Sync, E: Sync {
+        let prepared = PreparedQuery::try_new(query, query_root_index, equality)?;
+        let per_graph: Vec<Vec<MatchedComponents>> = corpus.graphs.par_iter()
+            .map(|graph| unwrap(match_prepared(&prepared, graph, None)))
+            .collect();
+        Ok(per_graph.into_iter()
+            .enumerate()
+            .flat_map(|(graph_id, matches)| matches.into_iter().map(move |matched_components| (graph_id, matched_components)))
+            .collect())
+    }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use graph::Graph;
+    use matching::Attributes;
+    use super::*;
+
+    fn word(word: &str) -> Attributes {
+        let mut attributes = HashMap::new();
+        attributes.insert("word".to_string(), word.to_string());
+        Some(attributes)
+    }
+
+    // a head with many dependents, every other one a match
+    fn sentence(length: usize) -> Graph {
+        let mut graph = Graph::new();
+        let head = graph.add_node("head".to_string(), word("see"));
+        for n in 0..length {
+            let text = if n % 2 == 0 { "dogs" } else { "cats" };
+            let dependent = graph.add_node(format!("token{}", n), word(text));
+            graph.add_edge(head, dependent, "dep".to_string(), None);
+        }
+        graph
+    }
+
+    fn query() -> Graph {
+        let mut query = Graph::new();
+        let head = query.add_node("head".to_string(), None);
+        let dependent = query.add_node("dependent".to_string(), word("dogs"));
+        query.add_edge(head, dependent, "dep".to_string(), None);
+        query
+    }
+
+    #[test]
+    fn same_order_as_serial() {
+        let mut dogs = Graph::new();
+        dogs.add_node("dependent".to_string(), word("dogs"));
+        let graph = sentence(200);
+        let matches = match_graph(&dogs, 0, &graph, None, &EqualityRequirement::Complete);
+        assert_eq!(::match_graph(&dogs, 0, &graph, None, &EqualityRequirement::Complete), matches);
+        assert_eq!(100, matches.len());
+        assert_eq!(matches, match_graph(&dogs, 0, &graph.freeze(), None, &EqualityRequirement::Complete));
+
+        let query = query();
+        // the first graph has no dependents and so no match, the others one each
+        let corpus: corpus::Corpus = (0..50).map(sentence).collect();
+        let matches = match_corpus(&query, 0, &corpus, &EqualityRequirement::Complete);
+        assert_eq!(::match_corpus(&query, 0, &corpus, &EqualityRequirement::Complete), matches);
+        assert_eq!(49, matches.len());
+        assert_eq!(Some(49), matches.last().map(|&(graph_id, _)| graph_id));
+    }
+
+    #[test]
+    fn invalid_query() {
+        let corpus = corpus::Corpus::new();
+        assert_eq!(Some(Error::InvalidQueryRoot(5)),
+                   try_match_corpus(&query(), 5, &corpus, &EqualityRequirement::Complete).err());
+    }
+}
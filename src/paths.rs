@@ -0,0 +1,301 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+use graph;
+use node;
+use edge;
+use error::Error;
+
+#[derive(Debug,PartialEq,Clone)]
+pub struct Path {
+    pub nodes: Vec<node::Index>,
+    pub edges: Vec<edge::Index>,
+}
+
+impl Path {
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+}
+
+pub enum Direction {
+    // only follow edges from their source to their target
+    Directed,
+    // follow edges either way, as when finding the path between two tokens
+    // that passes through their common head
+    Undirected,
+}
+
+// For every node, the edges that can be followed from it and the node each
+// leads to.
+fn adjacency<N, E>(graph: &graph::Graph<N, E>, direction: &Direction) -> Vec<Vec<(edge::Index, node::Index)>> {
+    let mut lists = vec![vec![]; graph.nodes.len()];
+    for node_index in graph.node_indices() {
+        for edge_index in graph.edges_for_node(node_index) {
+            lists[node_index].push((edge_index, graph.edges[edge_index].target));
+        }
+    }
+    if let Direction::Undirected = *direction {
+        for edge_index in 0..graph.edges.len() {
+            let edge = &graph.edges[edge_index];
+            if !edge.removed && edge.source != edge.target {
+                lists[edge.target].push((edge_index, edge.source));
+            }
+        }
+    }
+    lists
+}
+
+// Walks back along the recorded edges to rebuild the path ending at to.
+fn trace(from: node::Index, to: node::Index, previous: &[Option<(edge::Index, node::Index)>]) -> Path {
+    let mut path = Path { nodes: vec![to], edges: vec![] };
+    let mut current = to;
+    while current != from {
+        let (edge_index, node_index) = previous[current].expect("path is broken");
+        path.edges.push(edge_index);
+        path.nodes.push(node_index);
+        current = node_index;
+    }
+    path.nodes.reverse();
+    path.edges.reverse();
+    path
+}
+
+// The path with the fewest edges, found by breadth first search.
+pub fn shortest_path<N, E>(graph: &graph::Graph<N, E>, from: node::Index, to: node::Index, direction: &Direction)
+    -> Result<Option<Path>, Error> {
+        graph.check_node(from)?;
+        graph.check_node(to)?;
+        let lists = adjacency(graph, direction);
+        let mut previous: Vec<Option<(edge::Index, node::Index)>> = vec![None; graph.nodes.len()];
+        let mut discovered = vec![false; graph.nodes.len()];
+        let mut queue = VecDeque::new();
+        discovered[from] = true;
+        queue.push_back(from);
+
+        while let Some(node_index) = queue.pop_front() {
+            if node_index == to {
+                return Ok(Some(trace(from, to, &previous)));
+            }
+            for &(edge_index, next) in &lists[node_index] {
+                if !discovered[next] {
+                    discovered[next] = true;
+                    previous[next] = Some((edge_index, node_index));
+                    queue.push_back(next);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+#[derive(PartialEq)]
+struct Candidate {
+    cost: f64,
+    node: node::Index,
+}
+
+impl Eq for Candidate {}
+
+// reversed so that the binary heap pops the cheapest candidate first
+impl Ord for Candidate {
+    fn cmp(&self, other: &Candidate) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Candidate) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// The cheapest path by the sum of its edge weights, using Dijkstra's algorithm.
+// Edges the weight function returns None for are not followed, and a negative
+// or NaN weight is reported as Error::InvalidWeight.
+pub fn shortest_weighted_path<N, E, F>(graph: &graph::Graph<N, E>, from: node::Index, to: node::Index, direction: &Direction, weight: F)
+    -> Result<Option<(Path, f64)>, Error>
+    where F: Fn(&edge::Edge<E>) -> Option<f64> {
+        graph.check_node(from)?;
+        graph.check_node(to)?;
+        let lists = adjacency(graph, direction);
+        let mut previous: Vec<Option<(edge::Index, node::Index)>> = vec![None; graph.nodes.len()];
+        let mut costs: Vec<Option<f64>> = vec![None; graph.nodes.len()];
+        let mut done = vec![false; graph.nodes.len()];
+        let mut heap = BinaryHeap::new();
+        costs[from] = Some(0.0);
+        heap.push(Candidate { cost: 0.0, node: from });
+
+        while let Some(Candidate { cost, node: node_index }) = heap.pop() {
+            if done[node_index] {
+                continue;
+            }
+            done[node_index] = true;
+            if node_index == to {
+                return Ok(Some((trace(from, to, &previous), cost)));
+            }
+            for &(edge_index, next) in &lists[node_index] {
+                let edge_weight = match weight(&graph.edges[edge_index]) {
+                    Some(edge_weight) => edge_weight,
+                    None => continue,
+                };
+                if edge_weight.is_nan() || edge_weight < 0.0 {
+                    return Err(Error::InvalidWeight(edge_index));
+                }
+                let next_cost = cost + edge_weight;
+                let better = match costs[next] {
+                    Some(existing) => next_cost < existing,
+                    None => true,
+                };
+                if !done[next] && better {
+                    costs[next] = Some(next_cost);
+                    previous[next] = Some((edge_index, node_index));
+                    heap.push(Candidate { cost: next_cost, node: next });
+                }
+            }
+        }
+        Ok(None)
+    }
+
+// A weight function reading a numeric attribute from string attribute edges.
+pub fn attribute_weight(key: &str) -> impl Fn(&edge::Edge) -> Option<f64> + '_ {
+    move |edge: &edge::Edge| {
+        match edge.attributes {
+            Some(ref attributes) => attributes.get(key).and_then(|value| value.parse().ok()),
+            None => None,
+        }
+    }
+}
+
+// Every path from one node to another that visits no node twice, optionally
+// limited to paths of at most max_length edges.
+pub fn all_simple_paths<N, E>(graph: &graph::Graph<N, E>, from: node::Index, to: node::Index, max_length: Option<usize>, direction: &Direction)
+    -> Result<Vec<Path>, Error> {
+        graph.check_node(from)?;
+        graph.check_node(to)?;
+        let lists = adjacency(graph, direction);
+        let mut paths = vec![];
+        let mut on_path = vec![false; graph.nodes.len()];
+        let mut path = Path { nodes: vec![from], edges: vec![] };
+        // each entry is the position reached in a node's adjacency list
+        let mut stack = vec![0];
+        on_path[from] = true;
+
+        if from == to {
+            return Ok(vec![path]);
+        }
+
+        while let Some(position) = stack.pop() {
+            let node_index = *path.nodes.last().expect("path is never empty");
+            let within_limit = match max_length {
+                Some(max_length) => path.edges.len() < max_length,
+                None => true,
+            };
+            if position >= lists[node_index].len() || !within_limit {
+                on_path[node_index] = false;
+                path.nodes.pop();
+                path.edges.pop();
+                continue;
+            }
+            stack.push(position + 1);
+            let (edge_index, next) = lists[node_index][position];
+            if on_path[next] {
+                continue;
+            }
+            if next == to {
+                let mut found = path.clone();
+                found.nodes.push(next);
+                found.edges.push(edge_index);
+                paths.push(found);
+                continue;
+            }
+            on_path[next] = true;
+            path.nodes.push(next);
+            path.edges.push(edge_index);
+            stack.push(0);
+        }
+        Ok(paths)
+    }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use graph::Graph;
+    use super::*;
+
+    fn weighted(weight: &str) -> Option<HashMap<String, String>> {
+        let mut attributes = HashMap::new();
+        attributes.insert("weight".to_string(), weight.to_string());
+        Some(attributes)
+    }
+
+    // 0 -> 1 -> 2 -> 3 and a heavier shortcut 0 -> 3
+    fn chain() -> Graph {
+        let mut graph = Graph::new();
+        for n in 0..4 {
+            graph.add_node(format!("node{}", n), None);
+        }
+        graph.add_edge(0, 1, "edge0".to_string(), weighted("1"));
+        graph.add_edge(1, 2, "edge1".to_string(), weighted("1"));
+        graph.add_edge(2, 3, "edge2".to_string(), weighted("1"));
+        graph.add_edge(0, 3, "edge3".to_string(), weighted("5"));
+        graph
+    }
+
+    #[test]
+    fn unweighted() {
+        let graph = chain();
+        let path = shortest_path(&graph, 0, 3, &Direction::Directed).unwrap().unwrap();
+        assert_eq!(vec![0, 3], path.nodes);
+        assert_eq!(vec![3], path.edges);
+
+        assert_eq!(None, shortest_path(&graph, 3, 0, &Direction::Directed).unwrap());
+        let path = shortest_path(&graph, 1, 0, &Direction::Undirected).unwrap().unwrap();
+        assert_eq!(vec![1, 0], path.nodes);
+        assert_eq!(vec![0], path.edges);
+
+        let path = shortest_path(&graph, 1, 1, &Direction::Directed).unwrap().unwrap();
+        assert!(path.is_empty());
+    }
+
+    #[test]
+    fn weighted_by_attribute() {
+        let graph = chain();
+        let (path, cost) = shortest_weighted_path(&graph, 0, 3, &Direction::Directed, attribute_weight("weight")).unwrap().unwrap();
+        assert_eq!(vec![0, 1, 2, 3], path.nodes);
+        assert_eq!(vec![0, 1, 2], path.edges);
+        assert_eq!(3.0, cost);
+
+        let mut graph = chain();
+        graph.edges[1].attributes = weighted("-1");
+        assert_eq!(Err(Error::InvalidWeight(1)),
+                   shortest_weighted_path(&graph, 0, 3, &Direction::Directed, attribute_weight("weight")));
+
+        // edges without a weight are not followed
+        graph.edges[1].attributes = None;
+        let (path, cost) = shortest_weighted_path(&graph, 0, 3, &Direction::Directed, attribute_weight("weight")).unwrap().unwrap();
+        assert_eq!(vec![0, 3], path.nodes);
+        assert_eq!(5.0, cost);
+    }
+
+    #[test]
+    fn simple_paths() {
+        let mut graph = chain();
+        graph.add_edge(3, 0, "cycle".to_string(), None);
+
+        let paths = all_simple_paths(&graph, 0, 3, None, &Direction::Directed).unwrap();
+        assert_eq!(2, paths.len());
+        assert_eq!(vec![0, 1, 2, 3], paths[0].nodes);
+        assert_eq!(vec![0, 3], paths[1].nodes);
+
+        let paths = all_simple_paths(&graph, 0, 3, Some(2), &Direction::Directed).unwrap();
+        assert_eq!(1, paths.len());
+
+        let paths = all_simple_paths(&graph, 1, 3, None, &Direction::Undirected).unwrap();
+        assert_eq!(3, paths.len());
+        assert!(all_simple_paths(&graph, 0, 9, None, &Direction::Directed).is_err());
+    }
+}
@@ -0,0 +1,316 @@
+use graph;
+use node;
+use edge;
+use error::{Error, unwrap};
+use matching::{Component, EqualityRequirement, Matches, MatchedComponents};
+use view::GraphView;
+
+// A query plan starts the search from the query node the fewest graph nodes
+// match, such as a rare lemma, rather than from the query root, and reaches the
+// rest of the query by following query edges in either direction.
+//
+// Unlike match_graph, which takes the first graph edge matching each query
+// edge, the planned search tries every matching edge so a match is found
+// wherever one exists. Results are still reported from the query root, in the
+// order match_graph would list their components.
+
+// One query edge to follow, from a query node already matched to the node at
+// its other end. Reverse steps follow the edge from its target to its source.
+#[derive(Debug,PartialEq)]
+pub struct Step {
+    pub query_edge: edge::Index,
+    pub from: node::Index,
+    pub to: node::Index,
+    pub reverse: bool,
+}
+
+#[derive(Debug,PartialEq)]
+pub struct QueryPlan {
+    pub start: node::Index,
+    pub steps: Vec<Step>,
+    // for each query node, at most how many graph nodes it matches on its own,
+    // see estimate
+    pub estimates: Vec<Option<usize>>,
+}
+
+pub fn plan<QN, QE, G>(query: &graph::Graph<QN, QE>, graph: &G, equality: &EqualityRequirement)
+    -> Result<QueryPlan, Error>
+    where G: GraphView, QN: Matches<G::N> {
+        query.check_acyclic()?;
+        let mut estimates = vec![None; query.nodes.len()];
+        let mut node_count = None;
+        for query_index in query.node_indices() {
+            estimates[query_index] = Some(estimate(&query.nodes[query_index].attributes, graph, equality, &mut node_count));
+        }
+        let start = match query.node_indices().into_iter().min_by_key(|&query_index| estimates[query_index]) {
+            Some(start) => start,
+            None => return Ok(QueryPlan { start: 0, steps: vec![], estimates }),
+        };
+
+        let mut planned = vec![false; query.nodes.len()];
+        let mut used = vec![false; query.edges.len()];
+        let mut steps = vec![];
+        planned[start] = true;
+        loop {
+            // edges between two planned nodes only check a match so they are
+            // taken first, otherwise the edge leading to the most selective node
+            let mut best: Option<(bool, Option<usize>, Step)> = None;
+            for (edge_index, query_edge) in query.edges.iter().enumerate() {
+                if used[edge_index] || query_edge.removed {
+                    continue;
+                }
+                let step = if planned[query_edge.source] {
+                    Step { query_edge: edge_index, from: query_edge.source, to: query_edge.target, reverse: false }
+                } else if planned[query_edge.target] {
+                    Step { query_edge: edge_index, from: query_edge.target, to: query_edge.source, reverse: true }
+                } else {
+                    continue;
+                };
+                let opens = !planned[step.to];
+                let estimate = estimates[step.to];
+                let better = match best {
+                    Some((best_opens, best_estimate, _)) => (opens, estimate) < (best_opens, best_estimate),
+                    None => true,
+                };
+                if better {
+                    best = Some((opens, estimate, step));
+                }
+            }
+            match best {
+                Some((_, _, step)) => {
+                    used[step.query_edge] = true;
+                    planned[step.to] = true;
+                    steps.push(step);
+                }
+                None => break,
+            }
+        }
+        Ok(QueryPlan { start, steps, estimates })
+    }
+
+// Estimates come from the graph's attribute index without matching any node:
+// the shortest list among the pairs a query node requires bounds the nodes
+// having them all. Without an index, or with nothing to look up, every live
+// node is counted, once for the whole plan.
+fn estimate<QN, G>(attributes: &QN, graph: &G, equality: &EqualityRequirement, node_count: &mut Option<usize>) -> usize
+    where G: GraphView, QN: Matches<G::N> {
+        let bound = match *equality {
+            EqualityRequirement::Complete => graph.indexed_node_bound(&attributes.required_attributes()),
+            _ => None,
+        };
+        match bound {
+            Some(bound) => bound,
+            None => *node_count.get_or_insert_with(|| graph.node_count()),
+        }
+    }
+
+pub fn match_graph<QN, QE, G>(
+    query: &graph::Graph<QN, QE>, query_root_index: node::Index, graph: &G, equality: &EqualityRequirement)
+    -> Vec<MatchedComponents>
+    where G: GraphView, QN: Matches<G::N>, QE: Matches<G::E> {
+        unwrap(try_match_graph(query, query_root_index, graph, equality))
+    }
+
+pub fn try_match_graph<QN, QE, G>(
+    query: &graph::Graph<QN, QE>, query_root_index: node::Index, graph: &G, equality: &EqualityRequirement)
+    -> Result<Vec<MatchedComponents>, Error>
+    where G: GraphView, QN: Matches<G::N>, QE: Matches<G::E> {
+        ::check_query(query, query_root_index)?;
+        let query_plan = plan(query, graph, equality)?;
+        Ok(execute(&query_plan, query, query_root_index, graph, equality))
+    }
+
+struct Search<'a, QN: 'a, QE: 'a, G: 'a> {
+    query_plan: &'a QueryPlan,
+    query: &'a graph::Graph<QN, QE>,
+    query_root_index: node::Index,
+    graph: &'a G,
+    equality: &'a EqualityRequirement,
+    node_assignments: Vec<Option<node::Index>>,
+    edge_assignments: Vec<Option<edge::Index>>,
+    results: Vec<MatchedComponents>,
+}
+
+// Runs a plan made for this query, with results listed from query_root_index.
+// A plan can be reused for other graphs, though its choice of start node was
+// made for the graph it was planned against.
+pub fn execute<QN, QE, G>(
+    query_plan: &QueryPlan, query: &graph::Graph<QN, QE>, query_root_index: node::Index, graph: &G, equality: &EqualityRequirement)
+    -> Vec<MatchedComponents>
+    where G: GraphView, QN: Matches<G::N>, QE: Matches<G::E> {
+        // a query in several pieces can never be matched as a whole
+        let mut reached = vec![false; query.nodes.len()];
+        reached[query_plan.start] = true;
+        for step in &query_plan.steps {
+            reached[step.to] = true;
+        }
+        if query.node_indices().into_iter().any(|query_index| !reached[query_index]) || !query.contains_node(query_root_index) {
+            return vec![];
+        }
+        let mut search = Search {
+            query_plan,
+            query,
+            query_root_index,
+            graph,
+            equality,
+            node_assignments: vec![None; query.nodes.len()],
+            edge_assignments: vec![None; query.edges.len()],
+            results: vec![],
+        };
+        for start in ::candidate_roots(query, query_plan.start, graph, None, equality).unwrap_or_default() {
+            search.node_assignments[query_plan.start] = Some(start);
+            search.extend(0);
+        }
+
+        // ordered by the graph nodes matched, the root first as in match_graph
+        let mut results = search.results;
+        results.sort_by_key(|matched_components| {
+            let nodes: Vec<node::Index> = matched_components.list.iter().map(|component| component.node).collect();
+            let edges: Vec<Option<edge::Index>> = matched_components.list.iter().map(|component| component.from_edge).collect();
+            (nodes, edges)
+        });
+        results
+    }
+
+impl<'a, QN, QE, G> Search<'a, QN, QE, G>
+    where G: GraphView, QN: Matches<G::N>, QE: Matches<G::E> {
+        fn extend(&mut self, position: usize) {
+            let query_plan = self.query_plan;
+            let step = match query_plan.steps.get(position) {
+                Some(step) => step,
+                None => {
+                    let mut matched_components = MatchedComponents { list: vec![] };
+                    self.collect(self.query_root_index, None, &mut matched_components);
+                    if matched_components.list.len() == self.query.node_count() {
+                        self.results.push(matched_components);
+                    }
+                    return;
+                }
+            };
+            let from = self.node_assignments[step.from].expect("steps start from matched nodes");
+            let already_matched = self.node_assignments[step.to];
+            let graph = self.graph;
+            let graph_edges: Box<dyn Iterator<Item = edge::Index>> =
+                if step.reverse { Box::new(graph.incoming(from)) } else { Box::new(graph.outgoing(from)) };
+            for edge_index in graph_edges {
+                let graph_edge = &self.graph.edges()[edge_index];
+                if !self.query.edges[step.query_edge].matches(graph_edge, self.equality) {
+                    continue;
+                }
+                let to = if step.reverse { graph_edge.source } else { graph_edge.target };
+                match already_matched {
+                    Some(matched) => {
+                        if matched != to {
+                            continue;
+                        }
+                    }
+                    None => {
+                        if !self.query.nodes[step.to].matches(&self.graph.nodes()[to], self.equality) {
+                            continue;
+                        }
+                        self.node_assignments[step.to] = Some(to);
+                    }
+                }
+                self.edge_assignments[step.query_edge] = Some(edge_index);
+                self.extend(position + 1);
+            }
+            self.node_assignments[step.to] = already_matched;
+            self.edge_assignments[step.query_edge] = None;
+        }
+
+        // lists components in the order recusive_node_match does
+        fn collect(&self, query_index: node::Index, query_edge: Option<edge::Index>, matched_components: &mut MatchedComponents) {
+            matched_components.list.push(Component {
+                from_edge: query_edge.and_then(|query_edge| self.edge_assignments[query_edge]),
+                node: self.node_assignments[query_index].expect("every query node is matched"),
+            });
+            for edge_index in self.query.edges_for_node(query_index) {
+                self.collect(self.query.edges[edge_index].target, Some(edge_index), matched_components);
+            }
+        }
+    }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use graph::Graph;
+    use matching::Attributes;
+    use super::*;
+
+    fn word(word: &str) -> Attributes {
+        let mut attributes = HashMap::new();
+        attributes.insert("word".to_string(), word.to_string());
+        Some(attributes)
+    }
+
+    // "see" heads many clauses, only one of them with the rare word "aardvark"
+    fn graph() -> Graph {
+        let mut graph = Graph::new();
+        for n in 0..5 {
+            let head = graph.add_node(format!("see{}", n), word("see"));
+            let object = graph.add_node(format!("object{}", n), word(if n == 3 { "aardvark" } else { "dog" }));
+            graph.add_edge(head, object, "dobj".to_string(), None);
+        }
+        graph.index_attributes();
+        graph
+    }
+
+    fn query() -> Graph {
+        let mut query = Graph::new();
+        let head = query.add_node("head".to_string(), word("see"));
+        let object = query.add_node("object".to_string(), word("aardvark"));
+        query.add_edge(head, object, "dobj".to_string(), None);
+        query
+    }
+
+    #[test]
+    fn starts_from_rare_node() {
+        let graph = graph();
+        let query = query();
+        let query_plan = plan(&query, &graph, &EqualityRequirement::Complete).unwrap();
+        assert_eq!(1, query_plan.start);
+        assert_eq!(vec![Some(5), Some(1)], query_plan.estimates);
+        assert_eq!(vec![Step { query_edge: 0, from: 1, to: 0, reverse: true }], query_plan.steps);
+        // without an index every node is counted
+        let mut unindexed = Graph::new();
+        unindexed.add_node("see", word("see"));
+        assert_eq!(vec![Some(1), Some(1)], plan(&query, &unindexed, &EqualityRequirement::Complete).unwrap().estimates);
+
+        let matches = match_graph(&query, 0, &graph, &EqualityRequirement::Complete);
+        assert_eq!(::match_graph(&query, 0, &graph, None, &EqualityRequirement::Complete), matches);
+        assert_eq!(6, matches[0].list[0].node);
+        assert_eq!(Some(3), matches[0].list[1].from_edge);
+    }
+
+    #[test]
+    fn tries_every_edge() {
+        let mut graph = graph();
+        // a second object for the aardvark's head, linked before the aardvark
+        // so it is tried first
+        graph.remove_edge(3);
+        let object = graph.add_node("object5".to_string(), word("dog"));
+        graph.add_edge(6, object, "dobj".to_string(), None);
+        graph.add_edge(6, 7, "dobj".to_string(), None);
+        let query = query();
+        assert_eq!(0, ::match_graph(&query, 0, &graph, None, &EqualityRequirement::Complete).len());
+        assert_eq!(1, match_graph(&query, 0, &graph, &EqualityRequirement::Complete).len());
+    }
+
+    #[test]
+    fn frozen_graph() {
+        let graph = graph();
+        let query = query();
+        let expected = match_graph(&query, 0, &graph, &EqualityRequirement::Complete);
+        let frozen = graph.freeze();
+        assert_eq!(1, plan(&query, &frozen, &EqualityRequirement::Complete).unwrap().start);
+        assert_eq!(expected, match_graph(&query, 0, &frozen, &EqualityRequirement::Complete));
+    }
+
+    #[test]
+    fn disconnected_query() {
+        let graph = graph();
+        let mut query = query();
+        query.add_node("other".to_string(), word("dog"));
+        assert!(match_graph(&query, 0, &graph, &EqualityRequirement::Complete).is_empty());
+    }
+}
@@ -0,0 +1,390 @@
+use std::marker::PhantomData;
+use graph;
+use node;
+use edge;
+use error::{Error, unwrap};
+use matching::{Attributes, Component, EqualityRequirement, Matches, MatchedComponents, Requirements};
+use view::GraphView;
+
+// A query checked once so it can be run against many graphs of node payload N
+// and edge payload E. Each query node's outgoing edges are listed up front, and
+// the attributes each query node and edge requires are gathered into sorted
+// lists which payloads may match against in place of themselves, see
+// Matches::matches_compiled. match_graph and recusive_node_match run through
+// the same matcher, so the matches are the same as theirs.
+pub struct PreparedQuery<'q, QN: 'q, QE: 'q, N = Attributes, E = Attributes> {
+    query: &'q graph::Graph<QN, QE>,
+    root_index: node::Index,
+    equality: &'q EqualityRequirement,
+    // for each query node, its outgoing edges in the order given by
+    // edges_for_node
+    children: Vec<Vec<edge::Index>>,
+    node_requirements: Vec<Requirements>,
+    edge_requirements: Vec<Requirements>,
+    // for each query node, the fewest outgoing edges a graph node needs to
+    // take its place, see min_out_degree
+    min_out_degrees: Vec<usize>,
+    node_count: usize,
+    payloads: PhantomData<fn(&N, &E)>,
+}
+
+impl<'q, QN, QE, N, E> PreparedQuery<'q, QN, QE, N, E>
+    where QN: Matches<N>, QE: Matches<E> {
+    pub fn new(query: &'q graph::Graph<QN, QE>, root_index: node::Index, equality: &'q EqualityRequirement)
+        -> PreparedQuery<'q, QN, QE, N, E> {
+            unwrap(PreparedQuery::try_new(query, root_index, equality))
+        }
+
+    // Fails as try_match_graph does for an invalid root or a cyclic query.
+    pub fn try_new(query: &'q graph::Graph<QN, QE>, root_index: node::Index, equality: &'q EqualityRequirement)
+        -> Result<PreparedQuery<'q, QN, QE, N, E>, Error> {
+            ::check_query(query, root_index)?;
+            Ok(PreparedQuery::compile(query, root_index, equality))
+        }
+
+    // try_new without the checks, for recusive_node_match which never made them
+    pub(crate) fn compile(query: &'q graph::Graph<QN, QE>, root_index: node::Index, equality: &'q EqualityRequirement)
+        -> PreparedQuery<'q, QN, QE, N, E> {
+            let mut children = vec![vec![]; query.nodes.len()];
+            for query_index in query.node_indices() {
+                children[query_index] = query.edges_for_node(query_index);
+            }
+            let edge_requirements: Vec<Requirements> = query.edges.iter()
+                .map(|edge| Requirements::new(edge.attributes.required_attributes()))
+                .collect();
+            let min_out_degrees = children.iter()
+                .map(|query_edges| min_out_degree(query_edges, &edge_requirements, equality, QE::single_valued()))
+                .collect();
+            PreparedQuery {
+                query,
+                root_index,
+                equality,
+                children,
+                node_requirements: query.nodes.iter().map(|node| Requirements::new(node.attributes.required_attributes())).collect(),
+                edge_requirements,
+                min_out_degrees,
+                node_count: query.node_count(),
+                payloads: PhantomData,
+            }
+        }
+
+    pub fn query(&self) -> &'q graph::Graph<QN, QE> {
+        self.query
+    }
+
+    pub fn root_index(&self) -> node::Index {
+        self.root_index
+    }
+
+    pub fn execute<G>(&self, graph: &G, graph_root_index: Option<node::Index>) -> Vec<MatchedComponents>
+        where G: GraphView<N = N, E = E> {
+            unwrap(self.try_execute(graph, graph_root_index))
+        }
+
+    pub fn try_execute<G>(&self, graph: &G, graph_root_index: Option<node::Index>)
+        -> Result<Vec<MatchedComponents>, Error>
+        where G: GraphView<N = N, E = E> {
+            Ok(self.candidate_roots(graph, graph_root_index)?.into_iter()
+                .filter_map(|root_index| self.match_root(graph, root_index))
+                .collect())
+        }
+
+    pub fn candidate_roots<G>(&self, graph: &G, graph_root_index: Option<node::Index>)
+        -> Result<Vec<node::Index>, Error>
+        where G: GraphView<N = N, E = E> {
+            ::candidate_roots(self.query, self.root_index, graph, graph_root_index, self.equality)
+        }
+
+    // The match of the whole query with its root at the given graph node.
+    pub fn match_root<G>(&self, graph: &G, graph_root_index: node::Index) -> Option<MatchedComponents>
+        where G: GraphView<N = N, E = E> {
+            let mut list = Vec::with_capacity(self.node_count);
+            self.match_from(self.root_index, graph, graph_root_index, None, &mut list);
+            if list.len() == self.node_count { Some(MatchedComponents { list }) } else { None }
+        }
+
+    // Each query edge takes the first graph edge matching it, a mismatch there
+    // fails the node and the components of a failed node are dropped. Every
+    // edge is checked before descending into any of them. Returns whether the
+    // node matched.
+    pub(crate) fn match_from<G>(&self, query_index: node::Index, graph: &G, graph_index: node::Index,
+                                source_edge: Option<edge::Index>, list: &mut Vec<Component>) -> bool
+        where G: GraphView<N = N, E = E> {
+            graph.contains_node(graph_index) && self.enough_edges(query_index, graph, graph_index) &&
+                self.node_matches(query_index, graph, graph_index) &&
+                self.match_children(query_index, graph, graph_index, source_edge, list)
+        }
+
+    // match_from for a node known to match, as the targets of the edges
+    // children_feasible returns are
+    fn match_children<G>(&self, query_index: node::Index, graph: &G, graph_index: node::Index,
+                         source_edge: Option<edge::Index>, list: &mut Vec<Component>) -> bool
+        where G: GraphView<N = N, E = E> {
+            let edges = match self.children_feasible(query_index, graph, graph_index) {
+                Some(edges) => edges,
+                None => return false,
+            };
+            list.push(Component { from_edge: source_edge, node: graph_index });
+
+            for (&query_edge_index, edge_index) in self.children[query_index].iter().zip(edges) {
+                let query_target = self.query.edges[query_edge_index].target;
+                let target = graph.edges()[edge_index].target;
+                if self.enough_edges(query_target, graph, target) {
+                    self.match_children(query_target, graph, target, Some(edge_index), list);
+                }
+            }
+            true
+        }
+
+    // Checked before the attributes. Linked graphs walk at most
+    // min_out_degrees[query_index] of the node's edges for it, frozen ones
+    // read the degree off their offsets.
+    fn enough_edges<G>(&self, query_index: node::Index, graph: &G, graph_index: node::Index) -> bool
+        where G: GraphView<N = N, E = E> {
+            graph.has_outgoing(graph_index, self.min_out_degrees[query_index])
+        }
+
+    fn node_matches<G>(&self, query_index: node::Index, graph: &G, graph_index: node::Index) -> bool
+        where G: GraphView<N = N, E = E> {
+            self.query.nodes[query_index].attributes
+                .matches_compiled(&self.node_requirements[query_index], &graph.nodes()[graph_index].attributes, self.equality)
+        }
+
+    // A check that a graph node can take the place of a query node, made before
+    // matching descends into any of the node's children. Returns the graph
+    // edge each of the query node's edges follows, None when the node could
+    // never match. Each query edge must have a matching outgoing edge, found
+    // for all of them in one pass over the node's edges comparing edge labels
+    // only, and only then is each first matching edge checked to lead to a
+    // node matching the query edge's target.
+    fn children_feasible<G>(&self, query_index: node::Index, graph: &G, graph_index: node::Index)
+        -> Option<Vec<edge::Index>>
+        where G: GraphView<N = N, E = E> {
+            let children = &self.children[query_index];
+            if children.is_empty() {
+                return Some(vec![]);
+            }
+
+            let mut found = vec![None; children.len()];
+            let mut missing = children.len();
+            for edge_index in graph.outgoing(graph_index) {
+                let attributes = &graph.edges()[edge_index].attributes;
+                for (position, &query_edge_index) in children.iter().enumerate() {
+                    if found[position].is_none() &&
+                       self.query.edges[query_edge_index].attributes.matches_compiled(&self.edge_requirements[query_edge_index], attributes, self.equality) {
+                        found[position] = Some(edge_index);
+                        missing -= 1;
+                    }
+                }
+                if missing == 0 {
+                    break;
+                }
+            }
+            if missing > 0 {
+                return None;
+            }
+
+            let edges: Vec<edge::Index> = found.into_iter().flatten().collect();
+            let targets_match = children.iter().zip(&edges).all(|(&query_edge_index, &edge_index)| {
+                self.node_matches(self.query.edges[query_edge_index].target, graph, graph.edges()[edge_index].target)
+            });
+            if targets_match { Some(edges) } else { None }
+        }
+}
+
+// The fewest outgoing edges a graph node can have and still match a query node
+// with these edges. Matching may follow one graph edge for several query edges,
+// so the number of query edges is no bound. Under Complete equality though,
+// query edges requiring different values for one key need an edge each when
+// edge payloads hold one value per key, see Matches::single_valued. Otherwise,
+// and under Contains where one value can contain them all, the bound is one
+// edge.
+//
+// In-degrees give no bound at all: a query node is reached along a single
+// edge, whatever number of edges lead to the graph node in its place.
+fn min_out_degree(query_edges: &[edge::Index], edge_requirements: &[Requirements], equality: &EqualityRequirement,
+                  single_valued: bool) -> usize {
+    if query_edges.is_empty() {
+        return 0;
+    }
+    match *equality {
+        EqualityRequirement::Complete if single_valued => {
+            let mut pairs: Vec<&(String, String)> = query_edges.iter()
+                .flat_map(|&query_edge_index| edge_requirements[query_edge_index].pairs())
+                .collect();
+            pairs.sort();
+            pairs.dedup();
+            // the pairs being sorted, each key's values are a run
+            let mut most = 1;
+            let mut run = 0;
+            for (position, pair) in pairs.iter().enumerate() {
+                run = if position > 0 && pairs[position - 1].0 == pair.0 { run + 1 } else { 1 };
+                most = most.max(run);
+            }
+            most
+        }
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use graph::Graph;
+    use matching::Attributes;
+    use super::*;
+
+    fn word(word: &str) -> Attributes {
+        let mut attributes = HashMap::new();
+        attributes.insert("word".to_string(), word.to_string());
+        Some(attributes)
+    }
+
+    fn sentence(object: &str) -> Graph {
+        let mut graph = Graph::new();
+        let head = graph.add_node("head".to_string(), word("see"));
+        let subject = graph.add_node("subject".to_string(), word("I"));
+        let object = graph.add_node("object".to_string(), word(object));
+        graph.add_edge(head, subject, "nsubj".to_string(), relation("nsubj"));
+        graph.add_edge(head, object, "dobj".to_string(), relation("dobj"));
+        graph
+    }
+
+    fn relation(relation: &str) -> Attributes {
+        let mut attributes = HashMap::new();
+        attributes.insert("relation".to_string(), relation.to_string());
+        Some(attributes)
+    }
+
+    #[test]
+    fn reused_across_graphs() {
+        let mut query = Graph::new();
+        let head = query.add_node("head".to_string(), word("see"));
+        let object = query.add_node("object".to_string(), word("dog"));
+        query.add_edge(head, object, "dobj".to_string(), relation("dobj"));
+        let equality = EqualityRequirement::Complete;
+        let prepared = PreparedQuery::new(&query, 0, &equality);
+
+        for object in &["dog", "cat"] {
+            let graph = sentence(object);
+            assert_eq!(::match_graph(&query, 0, &graph, None, &equality), prepared.execute(&graph, None));
+        }
+        assert_eq!(1, prepared.execute(&sentence("dog"), Some(0)).len());
+        assert_eq!(None, prepared.match_root(&sentence("dog"), 1));
+        assert_eq!(Err(Error::InvalidNode(7)), prepared.try_execute(&sentence("dog"), Some(7)));
+    }
+
+    #[test]
+    fn failed_branches_are_dropped() {
+        // the object's child fails to match, leaving too few components
+        let mut query = Graph::new();
+        let head = query.add_node("head".to_string(), None);
+        let object = query.add_node("object".to_string(), None);
+        let child = query.add_node("child".to_string(), None);
+        query.add_edge(head, object, "dobj".to_string(), None);
+        query.add_edge(object, child, "amod".to_string(), None);
+        let equality = EqualityRequirement::Complete;
+        let prepared = PreparedQuery::new(&query, 0, &equality);
+        let graph = sentence("dog");
+        assert_eq!(::match_graph(&query, 0, &graph, None, &equality), prepared.execute(&graph, None));
+        assert!(prepared.execute(&graph, None).is_empty());
+    }
+
+    #[test]
+    fn children_checked_before_descending() {
+        let mut query = Graph::new();
+        let head = query.add_node("head".to_string(), word("see"));
+        let subject = query.add_node("subject".to_string(), word("I"));
+        let object = query.add_node("object".to_string(), word("dog"));
+        query.add_edge(head, subject, "nsubj".to_string(), relation("nsubj"));
+        query.add_edge(head, object, "dobj".to_string(), relation("dobj"));
+        let equality = EqualityRequirement::Complete;
+        let prepared = PreparedQuery::new(&query, head, &equality);
+
+        let graph = sentence("dog");
+        assert_eq!(Some(vec![0, 1]), prepared.children_feasible(head, &graph, 0));
+        // the object leaves no edge out of the head leading to a "dog"
+        let graph = sentence("cat");
+        assert_eq!(None, prepared.children_feasible(head, &graph, 0));
+        // a node without outgoing edges is ruled out at once
+        assert_eq!(None, prepared.children_feasible(head, &graph, 1));
+        assert_eq!(Some(vec![]), prepared.children_feasible(subject, &graph, 1));
+    }
+
+    #[test]
+    fn out_degree_bound() {
+        // two edges asking for different relations need two edges, two asking
+        // for the same one can both follow a single edge
+        let mut query = Graph::new();
+        let head = query.add_node("head".to_string(), None);
+        let first = query.add_node("first".to_string(), None);
+        let second = query.add_node("second".to_string(), None);
+        let third = query.add_node("third".to_string(), None);
+        query.add_edge(head, first, "nsubj".to_string(), relation("nsubj"));
+        query.add_edge(head, second, "nsubj".to_string(), relation("nsubj"));
+        query.add_edge(head, third, "dobj".to_string(), relation("dobj"));
+        query.add_edge(first, third, "det".to_string(), None);
+        let complete = EqualityRequirement::Complete;
+        let prepared: PreparedQuery<_, _> = PreparedQuery::new(&query, head, &complete);
+        assert_eq!(vec![2, 1, 0, 0], prepared.min_out_degrees);
+        let contains = EqualityRequirement::Contains;
+        let prepared: PreparedQuery<_, _> = PreparedQuery::new(&query, head, &contains);
+        assert_eq!(vec![1, 1, 0, 0], prepared.min_out_degrees);
+
+        let mut graph = sentence("dog");
+        graph.remove_edge(1);
+        let mut query = Graph::new();
+        let head = query.add_node("head".to_string(), None);
+        let first = query.add_node("first".to_string(), None);
+        let second = query.add_node("second".to_string(), None);
+        query.add_edge(head, first, "nsubj".to_string(), relation("nsubj"));
+        query.add_edge(head, second, "nsubj".to_string(), relation("nsubj"));
+        let prepared = PreparedQuery::new(&query, head, &complete);
+        assert_eq!(1, prepared.execute(&graph, Some(0)).len());
+        assert_eq!(prepared.execute(&graph, Some(0)), prepared.execute(&graph.freeze(), Some(0)));
+    }
+
+    // edge payloads which may carry a key more than once
+    struct Tags(Vec<(String, String)>);
+
+    impl Matches for Tags {
+        fn matches(&self, other: &Tags, _equality: &EqualityRequirement) -> bool {
+            self.0.iter().all(|pair| other.0.contains(pair))
+        }
+    }
+
+    fn tags(values: &[&str]) -> Tags {
+        Tags(values.iter().map(|value| ("relation".to_string(), value.to_string())).collect())
+    }
+
+    #[test]
+    fn multi_valued_edges() {
+        // one edge tagged with both relations matches both query edges
+        let mut graph: Graph<(), Tags> = Graph::default();
+        let head = graph.add_node("head".to_string(), ());
+        let dependent = graph.add_node("dependent".to_string(), ());
+        graph.add_edge(head, dependent, "both".to_string(), tags(&["nsubj", "dobj"]));
+        let mut query: Graph<(), Tags> = Graph::default();
+        let query_head = query.add_node("head".to_string(), ());
+        let subject = query.add_node("subject".to_string(), ());
+        let object = query.add_node("object".to_string(), ());
+        query.add_edge(query_head, subject, "nsubj".to_string(), tags(&["nsubj"]));
+        query.add_edge(query_head, object, "dobj".to_string(), tags(&["dobj"]));
+        let complete = EqualityRequirement::Complete;
+        let prepared: PreparedQuery<_, _, (), Tags> = PreparedQuery::new(&query, query_head, &complete);
+        assert_eq!(vec![1, 0, 0], prepared.min_out_degrees);
+        let matches = prepared.execute(&graph, None);
+        assert_eq!(1, matches.len());
+        assert_eq!(prepared.execute(&graph.freeze(), None), matches);
+    }
+
+    #[test]
+    fn invalid_query() {
+        let mut query = Graph::new();
+        query.add_node("node0".to_string(), None);
+        query.add_edge(0, 0, "loop".to_string(), None);
+        let equality = EqualityRequirement::Complete;
+        assert_eq!(Some(Error::Cycle(vec![0, 0])), PreparedQuery::<_, _, Attributes, Attributes>::try_new(&query, 0, &equality).err());
+        assert_eq!(Some(Error::InvalidQueryRoot(1)), PreparedQuery::<_, _, Attributes, Attributes>::try_new(&query, 1, &equality).err());
+    }
+}
@@ -0,0 +1,535 @@
+use graph;
+use node;
+use edge;
+use error::{Error, unwrap};
+use matching::{Attributes, AttributePairs, AttributesMut, EqualityRequirement, Matches, MatchedComponents};
+
+// Rewrite rules change a graph wherever a pattern matches, for example to
+// collapse a preposition node into the label of an edge between its head and
+// its object. A rule is a query graph, matched as match_graph matches it, and a
+// list of actions applied in order to each match.
+//
+// Actions refer to nodes and edges matched by the pattern through their index
+// in the pattern graph, and to nodes and edges added by earlier actions of the
+// same rule through the order they were added in.
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum NodeRef {
+    // the graph node matched by this pattern node
+    Matched(node::Index),
+    // the node added by the rule's add_node action with this position, from 0
+    Added(usize),
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum EdgeRef {
+    Matched(edge::Index),
+    Added(usize),
+}
+
+// The text an action sets, read from the graph as the action is applied.
+#[derive(Clone,Debug,PartialEq)]
+pub enum Value {
+    Text(String),
+    NodeIdentifier(NodeRef),
+    // fails with Error::MissingAttribute when the node does not have it
+    NodeAttribute(NodeRef, String),
+    EdgeIdentifier(EdgeRef),
+}
+
+// What a pass does with a match sharing graph nodes with one rewritten, and
+// changed, before it in the same pass.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Overlap {
+    // leave it for a later pass, see apply_to_fixpoint
+    Skip,
+    // fail with Error::OverlappingMatch
+    Fail,
+}
+
+enum Action {
+    AddNode(Value),
+    AddEdge(NodeRef, NodeRef, Value),
+    RemoveNode(NodeRef),
+    RemoveEdge(EdgeRef),
+    SetNodeAttribute(NodeRef, String, Value),
+    RemoveNodeAttribute(NodeRef, String),
+    SetEdgeAttribute(EdgeRef, String, Value),
+    RemoveEdgeAttribute(EdgeRef, String),
+    SetNodeIdentifier(NodeRef, Value),
+    SetEdgeIdentifier(EdgeRef, Value),
+    RetargetEdge(EdgeRef, NodeRef),
+}
+
+pub struct Rule<QN = Attributes, QE = Attributes> {
+    pattern: graph::Graph<QN, QE>,
+    root_index: node::Index,
+    equality: EqualityRequirement,
+    overlap: Overlap,
+    actions: Vec<Action>,
+    // the pattern node and edge behind each component of a match, in the
+    // order match_graph lists them
+    order: Vec<(node::Index, Option<edge::Index>)>,
+}
+
+impl<QN, QE> Rule<QN, QE> {
+    pub fn new(pattern: graph::Graph<QN, QE>, root_index: node::Index) -> Rule<QN, QE> {
+        unwrap(Rule::try_new(pattern, root_index))
+    }
+
+    // Fails as try_match_graph does for an invalid root or a cyclic pattern.
+    pub fn try_new(pattern: graph::Graph<QN, QE>, root_index: node::Index) -> Result<Rule<QN, QE>, Error> {
+        ::check_query(&pattern, root_index)?;
+        let mut order = vec![];
+        component_order(&pattern, root_index, None, &mut order);
+        Ok(Rule {
+            pattern,
+            root_index,
+            equality: EqualityRequirement::Complete,
+            overlap: Overlap::Skip,
+            actions: vec![],
+            order,
+        })
+    }
+
+    pub fn equality(mut self, equality: EqualityRequirement) -> Rule<QN, QE> {
+        self.equality = equality;
+        self
+    }
+
+    pub fn on_overlap(mut self, overlap: Overlap) -> Rule<QN, QE> {
+        self.overlap = overlap;
+        self
+    }
+
+    pub fn add_node(mut self, identifier: Value) -> Rule<QN, QE> {
+        self.actions.push(Action::AddNode(identifier));
+        self
+    }
+
+    pub fn add_edge(mut self, source: NodeRef, target: NodeRef, identifier: Value) -> Rule<QN, QE> {
+        self.actions.push(Action::AddEdge(source, target, identifier));
+        self
+    }
+
+    // Removes the node along with its edges, as Graph::remove_node does.
+    pub fn remove_node(mut self, node: NodeRef) -> Rule<QN, QE> {
+        self.actions.push(Action::RemoveNode(node));
+        self
+    }
+
+    pub fn remove_edge(mut self, edge: EdgeRef) -> Rule<QN, QE> {
+        self.actions.push(Action::RemoveEdge(edge));
+        self
+    }
+
+    pub fn set_node_attribute(mut self, node: NodeRef, key: &str, value: Value) -> Rule<QN, QE> {
+        self.actions.push(Action::SetNodeAttribute(node, key.to_string(), value));
+        self
+    }
+
+    pub fn remove_node_attribute(mut self, node: NodeRef, key: &str) -> Rule<QN, QE> {
+        self.actions.push(Action::RemoveNodeAttribute(node, key.to_string()));
+        self
+    }
+
+    pub fn set_edge_attribute(mut self, edge: EdgeRef, key: &str, value: Value) -> Rule<QN, QE> {
+        self.actions.push(Action::SetEdgeAttribute(edge, key.to_string(), value));
+        self
+    }
+
+    pub fn remove_edge_attribute(mut self, edge: EdgeRef, key: &str) -> Rule<QN, QE> {
+        self.actions.push(Action::RemoveEdgeAttribute(edge, key.to_string()));
+        self
+    }
+
+    pub fn relabel_node(mut self, node: NodeRef, identifier: Value) -> Rule<QN, QE> {
+        self.actions.push(Action::SetNodeIdentifier(node, identifier));
+        self
+    }
+
+    pub fn relabel_edge(mut self, edge: EdgeRef, identifier: Value) -> Rule<QN, QE> {
+        self.actions.push(Action::SetEdgeIdentifier(edge, identifier));
+        self
+    }
+
+    pub fn retarget_edge(mut self, edge: EdgeRef, target: NodeRef) -> Rule<QN, QE> {
+        self.actions.push(Action::RetargetEdge(edge, target));
+        self
+    }
+
+    pub fn pattern(&self) -> &graph::Graph<QN, QE> {
+        &self.pattern
+    }
+
+    // One pass: the graph is matched once and every match is rewritten in
+    // turn, apart from overlapping ones. Returns the number of matches whose
+    // rewrite changed the graph.
+    pub fn apply<N, E>(&self, graph: &mut graph::Graph<N, E>) -> usize
+        where QN: Matches<N>, QE: Matches<E>,
+              N: AttributesMut + AttributePairs + Default, E: AttributesMut + Default {
+            unwrap(self.try_apply(graph))
+        }
+
+    // Fails when an action refers to a node or edge the rule does not have,
+    // when a Value reads an attribute a node lacks, and on overlapping matches
+    // under Overlap::Fail. Matches rewritten before the failure stay rewritten.
+    pub fn try_apply<N, E>(&self, graph: &mut graph::Graph<N, E>) -> Result<usize, Error>
+        where QN: Matches<N>, QE: Matches<E>,
+              N: AttributesMut + AttributePairs + Default, E: AttributesMut + Default {
+            self.check()?;
+            let matches = ::try_match_graph(&self.pattern, self.root_index, &*graph, None, &self.equality)?;
+            let mut touched = vec![false; graph.nodes.len()];
+            let mut changed = 0;
+            for matched_components in matches {
+                if let Some(component) = matched_components.list.iter().find(|component| touched[component.node]) {
+                    match self.overlap {
+                        Overlap::Skip => continue,
+                        Overlap::Fail => return Err(Error::OverlappingMatch(component.node)),
+                    }
+                }
+                // a rewrite that changes nothing does not hold up the matches
+                // overlapping it
+                if self.rewrite(graph, &matched_components)? {
+                    for component in &matched_components.list {
+                        touched[component.node] = true;
+                    }
+                    changed += 1;
+                }
+            }
+            Ok(changed)
+        }
+
+    // actions may only refer to pattern nodes and edges, and to nodes and
+    // edges added by earlier actions
+    fn check(&self) -> Result<(), Error> {
+        let mut added_nodes = 0;
+        let mut added_edges = 0;
+        for action in &self.actions {
+            let (mut nodes, mut edges, value): (Vec<&NodeRef>, Vec<&EdgeRef>, Option<&Value>) = match *action {
+                Action::AddNode(ref value) => (vec![], vec![], Some(value)),
+                Action::AddEdge(ref source, ref target, ref value) => (vec![source, target], vec![], Some(value)),
+                Action::RemoveNode(ref node) |
+                Action::RemoveNodeAttribute(ref node, _) => (vec![node], vec![], None),
+                Action::RemoveEdge(ref edge) |
+                Action::RemoveEdgeAttribute(ref edge, _) => (vec![], vec![edge], None),
+                Action::SetNodeAttribute(ref node, _, ref value) |
+                Action::SetNodeIdentifier(ref node, ref value) => (vec![node], vec![], Some(value)),
+                Action::SetEdgeAttribute(ref edge, _, ref value) |
+                Action::SetEdgeIdentifier(ref edge, ref value) => (vec![], vec![edge], Some(value)),
+                Action::RetargetEdge(ref edge, ref node) => (vec![node], vec![edge], None),
+            };
+            match value {
+                Some(Value::NodeIdentifier(node)) | Some(Value::NodeAttribute(node, _)) => nodes.push(node),
+                Some(Value::EdgeIdentifier(edge)) => edges.push(edge),
+                _ => {}
+            }
+            for node in nodes {
+                match *node {
+                    NodeRef::Matched(index) => self.pattern.check_node(index)?,
+                    NodeRef::Added(position) if position >= added_nodes => return Err(Error::InvalidNode(position)),
+                    NodeRef::Added(_) => {}
+                }
+            }
+            for edge in edges {
+                match *edge {
+                    EdgeRef::Matched(index) => self.pattern.check_edge(index)?,
+                    EdgeRef::Added(position) if position >= added_edges => return Err(Error::InvalidEdge(position)),
+                    EdgeRef::Added(_) => {}
+                }
+            }
+            match *action {
+                Action::AddNode(_) => added_nodes += 1,
+                Action::AddEdge(..) => added_edges += 1,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Applies the actions to one match, returning whether the graph changed.
+    fn rewrite<N, E>(&self, graph: &mut graph::Graph<N, E>, matched_components: &MatchedComponents) -> Result<bool, Error>
+        where N: AttributesMut + AttributePairs + Default, E: AttributesMut + Default {
+            let mut binding = Binding {
+                nodes: vec![0; self.pattern.nodes.len()],
+                edges: vec![0; self.pattern.edges.len()],
+                added_nodes: vec![],
+                added_edges: vec![],
+            };
+            for (&(query_index, query_edge), component) in self.order.iter().zip(&matched_components.list) {
+                binding.nodes[query_index] = component.node;
+                if let (Some(query_edge), Some(edge_index)) = (query_edge, component.from_edge) {
+                    binding.edges[query_edge] = edge_index;
+                }
+            }
+
+            let mut changed = false;
+            for action in &self.actions {
+                changed |= match *action {
+                    Action::AddNode(ref identifier) => {
+                        let identifier = binding.value(graph, identifier)?;
+                        let node_index = graph.try_add_node(identifier, N::default())?;
+                        binding.added_nodes.push(node_index);
+                        true
+                    }
+                    Action::AddEdge(ref source, ref target, ref identifier) => {
+                        let identifier = binding.value(graph, identifier)?;
+                        let edge_index = graph.try_add_edge(binding.node(source), binding.node(target), identifier, E::default())?;
+                        binding.added_edges.push(edge_index);
+                        true
+                    }
+                    Action::RemoveNode(ref node) => {
+                        graph.try_remove_node(binding.node(node))?;
+                        true
+                    }
+                    Action::RemoveEdge(ref edge) => {
+                        graph.try_remove_edge(binding.edge(edge))?;
+                        true
+                    }
+                    Action::SetNodeAttribute(ref node, ref key, ref value) => {
+                        let value = binding.value(graph, value)?;
+                        graph.try_set_node_attribute(binding.node(node), key, &value)? != Some(value)
+                    }
+                    Action::RemoveNodeAttribute(ref node, ref key) => {
+                        graph.try_remove_node_attribute(binding.node(node), key)?.is_some()
+                    }
+                    Action::SetEdgeAttribute(ref edge, ref key, ref value) => {
+                        let value = binding.value(graph, value)?;
+                        graph.try_set_edge_attribute(binding.edge(edge), key, &value)? != Some(value)
+                    }
+                    Action::RemoveEdgeAttribute(ref edge, ref key) => {
+                        graph.try_remove_edge_attribute(binding.edge(edge), key)?.is_some()
+                    }
+                    Action::SetNodeIdentifier(ref node, ref identifier) => {
+                        let node_index = binding.node(node);
+                        graph.check_node(node_index)?;
+                        let identifier = binding.value(graph, identifier)?;
+                        let differs = graph.nodes[node_index].identifier != identifier;
+                        graph.try_set_node_identifier(node_index, identifier)?;
+                        differs
+                    }
+                    Action::SetEdgeIdentifier(ref edge, ref identifier) => {
+                        let edge_index = binding.edge(edge);
+                        graph.check_edge(edge_index)?;
+                        let identifier = binding.value(graph, identifier)?;
+                        let differs = graph.edges[edge_index].identifier != identifier;
+                        graph.try_set_edge_identifier(edge_index, identifier)?;
+                        differs
+                    }
+                    Action::RetargetEdge(ref edge, ref target) => {
+                        let edge_index = binding.edge(edge);
+                        let target = binding.node(target);
+                        graph.check_edge(edge_index)?;
+                        let differs = graph.edges[edge_index].target != target;
+                        graph.try_retarget_edge(edge_index, target)?;
+                        differs
+                    }
+                };
+            }
+            Ok(changed)
+        }
+}
+
+// Applies each rule in turn, one pass at a time, until a pass leaves the graph
+// unchanged. Returns the number of matches rewritten. Rules that keep changing
+// the graph, such as one adding a node wherever a node matches, fail with
+// Error::NoFixpoint after max_passes passes.
+pub fn apply_to_fixpoint<QN, QE, N, E>(rules: &[Rule<QN, QE>], graph: &mut graph::Graph<N, E>, max_passes: usize) -> usize
+    where QN: Matches<N>, QE: Matches<E>,
+          N: AttributesMut + AttributePairs + Default, E: AttributesMut + Default {
+        unwrap(try_apply_to_fixpoint(rules, graph, max_passes))
+    }
+
+pub fn try_apply_to_fixpoint<QN, QE, N, E>(rules: &[Rule<QN, QE>], graph: &mut graph::Graph<N, E>, max_passes: usize)
+    -> Result<usize, Error>
+    where QN: Matches<N>, QE: Matches<E>,
+          N: AttributesMut + AttributePairs + Default, E: AttributesMut + Default {
+        let mut total = 0;
+        for _ in 0..max_passes {
+            let mut changed = 0;
+            for rule in rules {
+                changed += rule.try_apply(graph)?;
+            }
+            if changed == 0 {
+                return Ok(total);
+            }
+            total += changed;
+        }
+        Err(Error::NoFixpoint(max_passes))
+    }
+
+// lists pattern nodes as recusive_node_match lists their matches
+fn component_order<QN, QE>(pattern: &graph::Graph<QN, QE>, query_index: node::Index, query_edge: Option<edge::Index>,
+                           order: &mut Vec<(node::Index, Option<edge::Index>)>) {
+    order.push((query_index, query_edge));
+    for edge_index in pattern.edges_for_node(query_index) {
+        component_order(pattern, pattern.edges[edge_index].target, Some(edge_index), order);
+    }
+}
+
+// The graph nodes and edges the references of one match's actions stand for.
+struct Binding {
+    nodes: Vec<node::Index>,
+    edges: Vec<edge::Index>,
+    added_nodes: Vec<node::Index>,
+    added_edges: Vec<edge::Index>,
+}
+
+impl Binding {
+    fn node(&self, node: &NodeRef) -> node::Index {
+        match *node {
+            NodeRef::Matched(query_index) => self.nodes[query_index],
+            NodeRef::Added(position) => self.added_nodes[position],
+        }
+    }
+
+    fn edge(&self, edge: &EdgeRef) -> edge::Index {
+        match *edge {
+            EdgeRef::Matched(query_index) => self.edges[query_index],
+            EdgeRef::Added(position) => self.added_edges[position],
+        }
+    }
+
+    fn value<N: AttributePairs, E>(&self, graph: &graph::Graph<N, E>, value: &Value) -> Result<String, Error> {
+        match *value {
+            Value::Text(ref text) => Ok(text.clone()),
+            Value::NodeIdentifier(ref node) => {
+                let node_index = self.node(node);
+                graph.check_node(node_index)?;
+                Ok(graph.nodes[node_index].identifier.to_string())
+            }
+            Value::NodeAttribute(ref node, ref key) => {
+                let node_index = self.node(node);
+                graph.check_node(node_index)?;
+                graph.nodes[node_index].attributes.attribute_pairs().into_iter()
+                    .find(|(k, _)| k == key)
+                    .map(|(_, v)| v)
+                    .ok_or_else(|| Error::MissingAttribute(node_index, key.clone()))
+            }
+            Value::EdgeIdentifier(ref edge) => {
+                let edge_index = self.edge(edge);
+                graph.check_edge(edge_index)?;
+                Ok(graph.edges[edge_index].identifier.to_string())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use graph::Graph;
+    use super::*;
+
+    fn word(word: &str) -> Attributes {
+        let mut attributes = HashMap::new();
+        attributes.insert("word".to_string(), word.to_string());
+        Some(attributes)
+    }
+
+    fn relation(relation: &str) -> Attributes {
+        let mut attributes = HashMap::new();
+        attributes.insert("relation".to_string(), relation.to_string());
+        Some(attributes)
+    }
+
+    // "sat on the mat" and "slept under the table"
+    fn sentences() -> Graph {
+        let mut graph = Graph::new();
+        for &(verb, preposition, object) in &[("sat", "on", "mat"), ("slept", "under", "table")] {
+            let head = graph.add_node(verb, word(verb));
+            let preposition = graph.add_node(preposition, word(preposition));
+            let object = graph.add_node(object, word(object));
+            graph.add_edge(head, preposition, "prep", relation("prep"));
+            graph.add_edge(preposition, object, "pobj", relation("pobj"));
+        }
+        graph
+    }
+
+    fn collapse_prepositions() -> Rule {
+        let mut pattern = Graph::new();
+        let head = pattern.add_node("head", None);
+        let preposition = pattern.add_node("preposition", None);
+        let object = pattern.add_node("object", None);
+        pattern.add_edge(head, preposition, "prep", relation("prep"));
+        pattern.add_edge(preposition, object, "pobj", relation("pobj"));
+        Rule::new(pattern, head)
+            .add_edge(NodeRef::Matched(head), NodeRef::Matched(object), Value::NodeAttribute(NodeRef::Matched(preposition), "word".to_string()))
+            .set_edge_attribute(EdgeRef::Added(0), "relation", Value::Text("prep".to_string()))
+            .remove_node(NodeRef::Matched(preposition))
+    }
+
+    #[test]
+    fn collapse() {
+        let mut graph = sentences();
+        let rule = collapse_prepositions();
+        assert_eq!(2, rule.apply(&mut graph));
+        assert_eq!(4, graph.node_count());
+        assert_eq!(vec![2], graph.successors(0).collect::<Vec<_>>());
+        assert_eq!("on", graph.edges[4].identifier);
+        assert_eq!("under", graph.edges[5].identifier);
+        assert_eq!("prep", graph.edges[5].attributes.as_ref().unwrap()["relation"]);
+        assert_eq!(0, rule.apply(&mut graph));
+    }
+
+    // each node's edge is marked, matches along the chain a -> b -> c overlap
+    fn mark_edges() -> Rule {
+        let mut pattern = Graph::new();
+        pattern.add_node("source", None);
+        pattern.add_node("target", None);
+        pattern.add_edge(0, 1, "edge", None);
+        Rule::new(pattern, 0).set_edge_attribute(EdgeRef::Matched(0), "seen", Value::EdgeIdentifier(EdgeRef::Matched(0)))
+    }
+
+    fn chain() -> Graph {
+        let mut graph = Graph::new();
+        for node in &["a", "b", "c"] {
+            graph.add_node(*node, None);
+        }
+        graph.add_edge(0, 1, "ab", None);
+        graph.add_edge(1, 2, "bc", None);
+        graph
+    }
+
+    #[test]
+    fn overlapping_matches() {
+        let mut graph = chain();
+        assert_eq!(1, mark_edges().apply(&mut graph));
+        assert_eq!(1, mark_edges().apply(&mut graph));
+        assert_eq!(0, mark_edges().apply(&mut graph));
+
+        let mut graph = chain();
+        assert_eq!(2, apply_to_fixpoint(&[mark_edges()], &mut graph, 5));
+        assert_eq!("bc", graph.edges[1].attributes.as_ref().unwrap()["seen"]);
+
+        let mut graph = chain();
+        let rule = mark_edges().on_overlap(Overlap::Fail);
+        assert_eq!(Err(Error::OverlappingMatch(1)), rule.try_apply(&mut graph));
+    }
+
+    fn any_node() -> Graph {
+        let mut pattern = Graph::new();
+        pattern.add_node("any", None);
+        pattern
+    }
+
+    #[test]
+    fn no_fixpoint() {
+        let rule = Rule::new(any_node(), 0).add_node(Value::NodeIdentifier(NodeRef::Matched(0)));
+        let mut graph = chain();
+        assert_eq!(Err(Error::NoFixpoint(3)), try_apply_to_fixpoint(&[rule], &mut graph, 3));
+        assert_eq!(3 * 8, graph.node_count());
+    }
+
+    #[test]
+    fn invalid_rules() {
+        let mut graph = sentences();
+        let rule = Rule::new(any_node(), 0).remove_node(NodeRef::Added(0));
+        assert_eq!(Err(Error::InvalidNode(0)), rule.try_apply(&mut graph));
+        let rule = Rule::new(any_node(), 0).remove_edge(EdgeRef::Matched(0));
+        assert_eq!(Err(Error::InvalidEdge(0)), rule.try_apply(&mut graph));
+        let rule = Rule::new(any_node(), 0).relabel_node(NodeRef::Matched(0), Value::NodeAttribute(NodeRef::Matched(0), "lemma".to_string()));
+        assert_eq!(Err(Error::MissingAttribute(0, "lemma".to_string())), rule.try_apply(&mut graph));
+        assert_eq!(6, graph.node_count());
+    }
+}
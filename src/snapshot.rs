@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::io;
+use std::io::{Read, Write};
+use std::str;
+use graph;
+use node;
+use edge;
+use symbols::SymbolTable;
+
+// Layout (all integers little endian):
+//
+//   magic "GMSN", u16 version, u16 reserved
+//   string table: u32 count, then count * (u32 length, utf-8 bytes)
+//   node table:   u32 count, then count * (u32 flags, u32 identifier, u32 first_outgoing_edge,
+//                 attributes)
+//   edge table:   u32 count, then count * (u32 flags, u32 identifier, u32 source, u32 target,
+//                 u32 next_outgoing_edge, attributes)
+//
+// where attributes is a u32 pair count followed by that many (u32 key, u32 value) string
+// references. NONE stands in for a missing edge link or a missing attribute map.
+const MAGIC: &[u8; 4] = b"GMSN";
+pub const VERSION: u16 = 1;
+const NONE: u32 = u32::MAX;
+const REMOVED: u32 = 1;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    InvalidUtf8,
+    InvalidReference,
+    TrailingBytes,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref err) => write!(f, "snapshot io error: {}", err),
+            Error::BadMagic => write!(f, "not a graph snapshot"),
+            Error::UnsupportedVersion(version) => write!(f, "unsupported snapshot version {}", version),
+            Error::Truncated => write!(f, "snapshot is truncated"),
+            Error::InvalidUtf8 => write!(f, "snapshot string table is not valid utf-8"),
+            Error::InvalidReference => write!(f, "snapshot contains an out of range reference"),
+            Error::TrailingBytes => write!(f, "snapshot has unexpected trailing bytes"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+pub fn write<W: Write>(graph: &graph::Graph, writer: &mut W) -> io::Result<()> {
+    let mut strings: Vec<&str> = vec![];
+    let mut symbols: HashMap<&str, u32> = HashMap::new();
+    let mut body: Vec<u8> = vec![];
+
+    write_u32(&mut body, try_len(graph.nodes.len())?);
+    for node in &graph.nodes {
+        write_u32(&mut body, flags(node.removed));
+        write_u32(&mut body, intern(&node.identifier, &mut strings, &mut symbols)?);
+        write_u32(&mut body, link(node.first_outgoing_edge)?);
+        write_attributes(&mut body, &node.attributes, &mut strings, &mut symbols)?;
+    }
+    write_u32(&mut body, try_len(graph.edges.len())?);
+    for edge in &graph.edges {
+        write_u32(&mut body, flags(edge.removed));
+        write_u32(&mut body, intern(&edge.identifier, &mut strings, &mut symbols)?);
+        write_u32(&mut body, try_len(edge.source)?);
+        write_u32(&mut body, try_len(edge.target)?);
+        write_u32(&mut body, link(edge.next_outgoing_edge)?);
+        write_attributes(&mut body, &edge.attributes, &mut strings, &mut symbols)?;
+    }
+
+    let mut header: Vec<u8> = vec![];
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.extend_from_slice(&0u16.to_le_bytes());
+    write_u32(&mut header, try_len(strings.len())?);
+    for string in &strings {
+        write_u32(&mut header, try_len(string.len())?);
+        header.extend_from_slice(string.as_bytes());
+    }
+
+    writer.write_all(&header)?;
+    writer.write_all(&body)
+}
+
+pub fn to_bytes(graph: &graph::Graph) -> Vec<u8> {
+    let mut bytes = vec![];
+    write(graph, &mut bytes).expect("graph is too large for a snapshot");
+    bytes
+}
+
+pub fn read<R: Read>(reader: &mut R) -> Result<graph::Graph, Error> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+    let snapshot = Snapshot::parse(&bytes)?;
+    Ok(snapshot.to_graph())
+}
+
+struct Entry {
+    removed: bool,
+    identifier: u32,
+    source: u32,
+    target: u32,
+    link: u32,
+    attributes: Option<(usize, usize)>,
+}
+
+// A validated view over the bytes of a snapshot. Strings are borrowed from the
+// underlying buffer, so inspecting a snapshot only allocates the offset tables.
+pub struct Snapshot<'a> {
+    strings: Vec<&'a str>,
+    nodes: Vec<Entry>,
+    edges: Vec<Entry>,
+    attributes: Vec<(u32, u32)>,
+}
+
+impl<'a> Snapshot<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Snapshot<'a>, Error> {
+        let mut cursor = Cursor { bytes, position: 0 };
+        let snapshot = Snapshot::parse_from(&mut cursor)?;
+        if cursor.position != bytes.len() {
+            return Err(Error::TrailingBytes);
+        }
+        Ok(snapshot)
+    }
+
+    fn parse_from(cursor: &mut Cursor<'a>) -> Result<Snapshot<'a>, Error> {
+        if cursor.take(4)? != MAGIC {
+            return Err(Error::BadMagic);
+        }
+        let version = cursor.u16()?;
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        cursor.u16()?;
+
+        let string_count = cursor.count(4)?;
+        let mut strings = Vec::with_capacity(string_count);
+        for _ in 0..string_count {
+            let length = cursor.u32()? as usize;
+            let bytes = cursor.take(length)?;
+            strings.push(str::from_utf8(bytes).map_err(|_| Error::InvalidUtf8)?);
+        }
+
+        let mut attributes = vec![];
+        let node_count = cursor.count(16)?;
+        let mut nodes = Vec::with_capacity(node_count);
+        for _ in 0..node_count {
+            let flags = cursor.u32()?;
+            let identifier = cursor.u32()?;
+            let link = cursor.u32()?;
+            let attrs = cursor.attributes(&mut attributes)?;
+            nodes.push(Entry { removed: flags & REMOVED != 0, identifier, source: NONE, target: NONE, link, attributes: attrs });
+        }
+        let edge_count = cursor.count(24)?;
+        let mut edges = Vec::with_capacity(edge_count);
+        for _ in 0..edge_count {
+            let flags = cursor.u32()?;
+            let identifier = cursor.u32()?;
+            let source = cursor.u32()?;
+            let target = cursor.u32()?;
+            let link = cursor.u32()?;
+            let attrs = cursor.attributes(&mut attributes)?;
+            edges.push(Entry { removed: flags & REMOVED != 0, identifier, source, target, link, attributes: attrs });
+        }
+
+        let snapshot = Snapshot { strings, nodes, edges, attributes };
+        snapshot.validate()?;
+        snapshot.validate_edge_lists()?;
+        Ok(snapshot)
+    }
+
+    // Every reference must be in range, and each edge may only be linked to
+    // once so that walking the outgoing edge lists is guaranteed to terminate.
+    fn validate(&self) -> Result<(), Error> {
+        let string_count = self.strings.len() as u32;
+        let mut linked = vec![false; self.edges.len()];
+        for entry in self.nodes.iter().chain(self.edges.iter()) {
+            if entry.identifier >= string_count {
+                return Err(Error::InvalidReference);
+            }
+            if entry.target != NONE && entry.target as usize >= self.nodes.len() {
+                return Err(Error::InvalidReference);
+            }
+            if entry.link != NONE {
+                match linked.get_mut(entry.link as usize) {
+                    Some(seen) if !*seen => *seen = true,
+                    _ => return Err(Error::InvalidReference),
+                }
+            }
+        }
+        for edge in &self.edges {
+            if edge.target == NONE || edge.source as usize >= self.nodes.len() {
+                return Err(Error::InvalidReference);
+            }
+        }
+        for &(key, value) in &self.attributes {
+            if key >= string_count || value >= string_count {
+                return Err(Error::InvalidReference);
+            }
+        }
+        Ok(())
+    }
+
+    // Each live edge must sit in the outgoing list of its source node, and
+    // tombstones must be unlinked, matching what Graph::remove_edge leaves.
+    fn validate_edge_lists(&self) -> Result<(), Error> {
+        let mut listed = 0;
+        for index in 0..self.nodes.len() {
+            let mut link = self.nodes[index].link;
+            if self.nodes[index].removed && link != NONE {
+                return Err(Error::InvalidReference);
+            }
+            while link != NONE {
+                let edge = &self.edges[link as usize];
+                if edge.removed || edge.source as usize != index || self.nodes[edge.target as usize].removed {
+                    return Err(Error::InvalidReference);
+                }
+                listed += 1;
+                link = edge.link;
+            }
+        }
+        for edge in &self.edges {
+            if edge.removed && edge.link != NONE {
+                return Err(Error::InvalidReference);
+            }
+        }
+        if listed != self.edges.iter().filter(|edge| !edge.removed).count() {
+            return Err(Error::InvalidReference);
+        }
+        Ok(())
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn node_identifier(&self, index: node::Index) -> &'a str {
+        self.strings[self.nodes[index].identifier as usize]
+    }
+
+    pub fn node_attribute(&self, index: node::Index, key: &str) -> Option<&'a str> {
+        self.attribute(&self.nodes[index], key)
+    }
+
+    pub fn edge_identifier(&self, index: edge::Index) -> &'a str {
+        self.strings[self.edges[index].identifier as usize]
+    }
+
+    pub fn edge_target(&self, index: edge::Index) -> node::Index {
+        self.edges[index].target as usize
+    }
+
+    pub fn edge_attribute(&self, index: edge::Index, key: &str) -> Option<&'a str> {
+        self.attribute(&self.edges[index], key)
+    }
+
+    fn attribute(&self, entry: &Entry, key: &str) -> Option<&'a str> {
+        let (start, end) = entry.attributes?;
+        self.attributes[start..end].iter()
+            .find(|&&(k, _)| self.strings[k as usize] == key)
+            .map(|&(_, v)| self.strings[v as usize])
+    }
+
+    fn owned_attributes(&self, entry: &Entry) -> Option<HashMap<String, String>> {
+        let (start, end) = entry.attributes?;
+        Some(self.attributes[start..end].iter()
+            .map(|&(k, v)| (self.strings[k as usize].to_string(), self.strings[v as usize].to_string()))
+            .collect())
+    }
+
+    pub fn to_graph(&self) -> graph::Graph {
+        // identifiers repeated in the snapshot share one string in the graph
+        let mut symbols = SymbolTable::new();
+        let nodes = self.nodes.iter()
+            .map(|entry| node::Node {
+                identifier: symbols.intern(self.strings[entry.identifier as usize]),
+                attributes: self.owned_attributes(entry),
+                first_outgoing_edge: unlink(entry.link),
+                first_incoming_edge: None,
+                removed: entry.removed,
+            })
+            .collect();
+        let edges = self.edges.iter()
+            .map(|entry| edge::Edge {
+                identifier: symbols.intern(self.strings[entry.identifier as usize]),
+                source: entry.source as usize,
+                target: entry.target as usize,
+                attributes: self.owned_attributes(entry),
+                next_outgoing_edge: unlink(entry.link),
+                next_incoming_edge: None,
+                removed: entry.removed,
+            })
+            .collect();
+        let mut graph = graph::Graph::new();
+        graph.nodes = nodes;
+        graph.edges = edges;
+        // incoming edge lists are not stored, they follow from the targets
+        graph.relink_incoming_edges();
+        graph
+    }
+}
+
+// Iterates over snapshots written back to back into a single buffer, as is
+// done when a whole collection of graphs is stored in one file.
+pub struct Snapshots<'a> {
+    cursor: Cursor<'a>,
+}
+
+pub fn snapshots(bytes: &[u8]) -> Snapshots<'_> {
+    Snapshots { cursor: Cursor { bytes, position: 0 } }
+}
+
+impl<'a> Iterator for Snapshots<'a> {
+    type Item = Result<Snapshot<'a>, Error>;
+
+    fn next(&mut self) -> Option<Result<Snapshot<'a>, Error>> {
+        if self.cursor.position == self.cursor.bytes.len() {
+            return None;
+        }
+        let result = Snapshot::parse_from(&mut self.cursor);
+        if result.is_err() {
+            // a corrupt snapshot leaves no way to find the next one
+            self.cursor.position = self.cursor.bytes.len();
+        }
+        Some(result)
+    }
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() - self.position < length {
+            return Err(Error::Truncated);
+        }
+        let slice = &self.bytes[self.position..self.position + length];
+        self.position += length;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    // Reads a table length, rejecting counts that could not possibly fit in the
+    // remaining input before anything is allocated for them.
+    fn count(&mut self, min_entry_size: usize) -> Result<usize, Error> {
+        let count = self.u32()? as usize;
+        if count.saturating_mul(min_entry_size) > self.bytes.len() - self.position {
+            return Err(Error::Truncated);
+        }
+        Ok(count)
+    }
+
+    fn attributes(&mut self, attributes: &mut Vec<(u32, u32)>) -> Result<Option<(usize, usize)>, Error> {
+        let count = self.u32()?;
+        if count == NONE {
+            return Ok(None);
+        }
+        let count = count as usize;
+        if count.saturating_mul(8) > self.bytes.len() - self.position {
+            return Err(Error::Truncated);
+        }
+        let start = attributes.len();
+        for _ in 0..count {
+            let key = self.u32()?;
+            let value = self.u32()?;
+            attributes.push((key, value));
+        }
+        Ok(Some((start, attributes.len())))
+    }
+}
+
+fn flags(removed: bool) -> u32 {
+    if removed { REMOVED } else { 0 }
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn try_len(length: usize) -> io::Result<u32> {
+    if length >= NONE as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "graph is too large for a snapshot"));
+    }
+    Ok(length as u32)
+}
+
+fn link(index: Option<edge::Index>) -> io::Result<u32> {
+    match index {
+        Some(index) => try_len(index),
+        None => Ok(NONE),
+    }
+}
+
+fn unlink(index: u32) -> Option<edge::Index> {
+    match index {
+        NONE => None,
+        index => Some(index as usize),
+    }
+}
+
+fn intern<'g>(string: &'g str, strings: &mut Vec<&'g str>, symbols: &mut HashMap<&'g str, u32>) -> io::Result<u32> {
+    if let Some(&symbol) = symbols.get(string) {
+        return Ok(symbol);
+    }
+    let symbol = try_len(strings.len())?;
+    strings.push(string);
+    symbols.insert(string, symbol);
+    Ok(symbol)
+}
+
+fn write_attributes<'g>(bytes: &mut Vec<u8>,
+                        attributes: &'g Option<HashMap<String, String>>,
+                        strings: &mut Vec<&'g str>,
+                        symbols: &mut HashMap<&'g str, u32>)
+                        -> io::Result<()> {
+    match *attributes {
+        Some(ref attrs) => {
+            write_u32(bytes, try_len(attrs.len())?);
+            // sorted so that equal graphs give equal bytes, whatever order
+            // their maps iterate in
+            let mut pairs: Vec<(&'g String, &'g String)> = attrs.iter().collect();
+            pairs.sort();
+            for (key, value) in pairs {
+                write_u32(bytes, intern(key, strings, symbols)?);
+                write_u32(bytes, intern(value, strings, symbols)?);
+            }
+        }
+        None => write_u32(bytes, NONE),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use graph::Graph;
+    use super::*;
+
+    fn sample_graph() -> Graph {
+        let mut attributes = HashMap::new();
+        attributes.insert("pos".to_string(), "NN".to_string());
+
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), Some(attributes.clone()));
+        let node1 = graph.add_node("node1".to_string(), None);
+        let node2 = graph.add_node("node2".to_string(), Some(attributes.clone()));
+        graph.add_edge(node0, node1, "edge0".to_string(), None);
+        graph.add_edge(node0, node2, "edge1".to_string(), Some(attributes));
+        graph
+    }
+
+    #[test]
+    fn round_trip() {
+        let graph = sample_graph();
+        let copy = read(&mut &to_bytes(&graph)[..]).unwrap();
+
+        assert_eq!(graph.nodes.len(), copy.nodes.len());
+        assert_eq!(graph.edges.len(), copy.edges.len());
+        for (a, b) in graph.nodes.iter().zip(copy.nodes.iter()) {
+            assert_eq!(a.identifier, b.identifier);
+            assert_eq!(a.attributes, b.attributes);
+            assert_eq!(a.first_outgoing_edge, b.first_outgoing_edge);
+            assert_eq!(a.removed, b.removed);
+        }
+        for (a, b) in graph.edges.iter().zip(copy.edges.iter()) {
+            assert_eq!(a.identifier, b.identifier);
+            assert_eq!(a.target, b.target);
+            assert_eq!(a.attributes, b.attributes);
+            assert_eq!(a.next_outgoing_edge, b.next_outgoing_edge);
+            assert_eq!(a.source, b.source);
+            assert_eq!(a.removed, b.removed);
+        }
+        assert_eq!(vec![0, 1], copy.edges_for_node(0));
+    }
+
+    #[test]
+    fn deterministic() {
+        // separately built maps with the same pairs rarely iterate in the
+        // same order
+        let graphs: Vec<Graph> = (0..2).map(|_| {
+            let attributes: HashMap<String, String> = (0..16).map(|n| (format!("key{}", n), format!("value{}", n))).collect();
+            let mut graph = sample_graph();
+            graph.add_node("node3".to_string(), Some(attributes.clone()));
+            graph.add_edge(0, 3, "edge2".to_string(), Some(attributes));
+            graph
+        }).collect();
+        assert_eq!(graphs[0], graphs[1]);
+        assert_eq!(to_bytes(&graphs[0]), to_bytes(&graphs[1]));
+        assert_eq!(to_bytes(&graphs[0]), to_bytes(&graphs[0]));
+    }
+
+    #[test]
+    fn round_trip_tombstones() {
+        let mut graph = sample_graph();
+        graph.remove_node(1);
+        let copy = read(&mut &to_bytes(&graph)[..]).unwrap();
+
+        assert!(!copy.contains_node(1));
+        assert!(!copy.contains_edge(0));
+        assert_eq!(vec![0, 2], copy.node_indices());
+        assert_eq!(vec![1], copy.edges_for_node(0));
+        assert_eq!(vec![1], copy.incoming_edges_for_node(2));
+        assert_eq!(graph, copy);
+    }
+
+    #[test]
+    fn truncated_and_corrupt() {
+        let bytes = to_bytes(&sample_graph());
+        for length in 0..bytes.len() {
+            assert!(Snapshot::parse(&bytes[..length]).is_err());
+        }
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        match Snapshot::parse(&bad_magic) {
+            Err(Error::BadMagic) => {}
+            _ => panic!(),
+        }
+
+        let mut bad_version = bytes.clone();
+        bad_version[4] = 9;
+        match Snapshot::parse(&bad_version) {
+            Err(Error::UnsupportedVersion(9)) => {}
+            _ => panic!(),
+        }
+
+        // point the last edge at a node that does not exist
+        let mut bad_target = bytes.clone();
+        let offset = bad_target.len() - 20;
+        bad_target[offset..offset + 4].copy_from_slice(&7u32.to_le_bytes());
+        match Snapshot::parse(&bad_target) {
+            Err(Error::InvalidReference) => {}
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn multiple_snapshots() {
+        let mut bytes = to_bytes(&sample_graph());
+        write(&Graph::new(), &mut bytes).unwrap();
+
+        let counts: Vec<usize> = snapshots(&bytes).map(|s| s.unwrap().node_count()).collect();
+        assert_eq!(vec![3, 0], counts);
+        assert!(Snapshot::parse(&bytes).is_err());
+    }
+}
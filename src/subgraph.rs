@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use graph;
+use node;
+use edge;
+use error::Error;
+use matching::Attributes;
+
+pub enum EdgeSelection {
+    // only the edges followed while expanding
+    Traversed,
+    // every edge between two of the expanded nodes
+    Induced,
+}
+
+// A standalone copy of part of a graph. nodes and edges hold the index in the
+// original graph of each node and edge in the copy, so that matches found in
+// the subgraph can be mapped back.
+pub struct Subgraph<N = Attributes, E = Attributes> {
+    pub graph: graph::Graph<N, E>,
+    pub nodes: Vec<node::Index>,
+    pub edges: Vec<edge::Index>,
+    node_lookup: HashMap<node::Index, node::Index>,
+}
+
+impl<N, E> Subgraph<N, E> {
+    pub fn original_node(&self, node_index: node::Index) -> node::Index {
+        self.nodes[node_index]
+    }
+
+    pub fn original_edge(&self, edge_index: edge::Index) -> edge::Index {
+        self.edges[edge_index]
+    }
+
+    pub fn subgraph_node(&self, original_index: node::Index) -> Option<node::Index> {
+        self.node_lookup.get(&original_index).cloned()
+    }
+}
+
+// Copies the listed nodes and edges into a new graph. Edges are added in the
+// order of their original indexes, and ordered by the same attribute when the
+// graph orders its edges by one, so that each node's outgoing edges keep their
+// relative order.
+pub fn build<N: Clone, E: Clone>(graph: &graph::Graph<N, E>, node_list: &[node::Index], edge_list: &[edge::Index])
+    -> Result<Subgraph<N, E>, Error> {
+        let mut subgraph = Subgraph {
+            graph: graph::Graph::default(),
+            nodes: vec![],
+            edges: vec![],
+            node_lookup: HashMap::new(),
+        };
+        for &node_index in node_list {
+            graph.check_node(node_index)?;
+            if subgraph.node_lookup.contains_key(&node_index) {
+                continue;
+            }
+            let node = &graph.nodes[node_index];
+            let index = subgraph.graph.add_node(node.identifier.clone(), node.attributes.clone());
+            subgraph.nodes.push(node_index);
+            subgraph.node_lookup.insert(node_index, index);
+        }
+
+        subgraph.graph.order_edges_like(graph);
+        let mut edge_list = edge_list.to_vec();
+        edge_list.sort();
+        edge_list.dedup();
+        for edge_index in edge_list {
+            graph.check_edge(edge_index)?;
+            let edge = &graph.edges[edge_index];
+            let source = subgraph.subgraph_node(edge.source).ok_or(Error::InvalidNode(edge.source))?;
+            let target = subgraph.subgraph_node(edge.target).ok_or(Error::InvalidNode(edge.target))?;
+            subgraph.graph.add_edge(source, target, edge.identifier.clone(), edge.attributes.clone());
+            subgraph.edges.push(edge_index);
+        }
+        Ok(subgraph)
+    }
+
+pub fn induced<N: Clone, E: Clone>(graph: &graph::Graph<N, E>, node_list: &[node::Index])
+    -> Result<Subgraph<N, E>, Error> {
+        let mut included = vec![false; graph.nodes.len()];
+        for &node_index in node_list {
+            graph.check_node(node_index)?;
+            included[node_index] = true;
+        }
+        let edge_list: Vec<edge::Index> = (0..graph.edges.len())
+            .filter(|&edge_index| {
+                let edge = &graph.edges[edge_index];
+                !edge.removed && included[edge.source] && included[edge.target]
+            })
+            .collect();
+        build(graph, node_list, &edge_list)
+    }
+
+#[cfg(test)]
+mod tests {
+    use graph::Graph;
+    use super::*;
+
+    #[test]
+    fn induced_subgraph() {
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), None);
+        let node1 = graph.add_node("node1".to_string(), None);
+        let node2 = graph.add_node("node2".to_string(), None);
+        graph.add_edge(node0, node1, "edge0".to_string(), None);
+        graph.add_edge(node1, node2, "edge1".to_string(), None);
+        graph.add_edge(node2, node1, "edge2".to_string(), None);
+
+        let subgraph = induced(&graph, &[node2, node1]).unwrap();
+        assert_eq!(2, subgraph.graph.node_count());
+        assert_eq!("node2", subgraph.graph.nodes[0].identifier);
+        assert_eq!(vec![1, 2], subgraph.edges);
+        assert_eq!(node1, subgraph.original_node(1));
+        assert_eq!(Some(0), subgraph.subgraph_node(node2));
+        assert_eq!(None, subgraph.subgraph_node(node0));
+        assert_eq!(vec![1], subgraph.graph.edges_for_node(0).iter()
+                   .map(|&edge_index| subgraph.graph.edges[edge_index].target).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn keeps_edge_order() {
+        let mut graph = Graph::new();
+        let head = graph.add_node("head".to_string(), None);
+        for (n, position) in ["3", "1", "2"].iter().enumerate() {
+            let dependent = graph.add_node(format!("node{}", n), None);
+            let edge_index = graph.add_edge(head, dependent, format!("edge{}", n), None);
+            graph.set_edge_attribute(edge_index, "position", position);
+        }
+        graph.order_edges_by_attribute("position");
+        assert_eq!(vec![1, 2, 0], graph.edges_for_node(head));
+
+        let mut subgraph = induced(&graph, &graph.node_indices()).unwrap();
+        assert_eq!(graph.edges_for_node(head), subgraph.graph.edges_for_node(0).iter()
+                   .map(|&edge_index| subgraph.original_edge(edge_index)).collect::<Vec<_>>());
+        // and edges added to the copy are placed by the attribute as well
+        let edge_index = subgraph.graph.add_edge(0, 1, "edge3".to_string(), None);
+        subgraph.graph.set_edge_attribute(edge_index, "position", "0");
+        assert_eq!(vec![3, 1, 2, 0], subgraph.graph.edges_for_node(0));
+    }
+
+    #[test]
+    fn edges_need_both_ends() {
+        let mut graph = Graph::new();
+        let node0 = graph.add_node("node0".to_string(), None);
+        let node1 = graph.add_node("node1".to_string(), None);
+        let edge0 = graph.add_edge(node0, node1, "edge0".to_string(), None);
+
+        assert_eq!(Some(Error::InvalidNode(node1)), build(&graph, &[node0], &[edge0]).err());
+    }
+}
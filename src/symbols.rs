@@ -0,0 +1,374 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
+use matching::{self, AttributePairs, AttributesMut, EqualityRequirement, Matches, Requirements};
+
+// An immutable, cheaply cloned string. Symbols interned by the same
+// SymbolTable share one allocation per distinct string, so equal symbols from
+// one table compare by pointer, and the repeated part of speech tags and
+// relation labels of a corpus are stored once. Symbols from different tables,
+// or made without one, are compared by their text.
+#[derive(Clone)]
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    // Whether both symbols share the same interned string.
+    pub fn ptr_eq(a: &Symbol, b: &Symbol) -> bool {
+        Arc::ptr_eq(&a.0, &b.0)
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        Symbol::ptr_eq(self, other) || self.0 == other.0
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Symbol) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Symbol) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(string: String) -> Symbol {
+        Symbol(Arc::from(string))
+    }
+}
+
+impl<'a> From<&'a str> for Symbol {
+    fn from(string: &'a str) -> Symbol {
+        Symbol(Arc::from(string))
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Symbol {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Symbol {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl PartialEq<Symbol> for str {
+    fn eq(&self, other: &Symbol) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<Symbol> for &str {
+    fn eq(&self, other: &Symbol) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl PartialEq<Symbol> for String {
+    fn eq(&self, other: &Symbol) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+// Hands out one Symbol per distinct string. A table can be kept by a graph,
+// see Graph::intern_identifiers, or shared by the code building many graphs.
+#[derive(Default)]
+pub struct SymbolTable {
+    symbols: HashSet<Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    pub fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(string) {
+            return symbol.clone();
+        }
+        let symbol = Symbol::from(string);
+        self.symbols.insert(symbol.clone());
+        symbol
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn intern_attributes(&mut self, attributes: &HashMap<String, String>) -> InternedAttributes {
+        let mut pairs: Vec<(Symbol, Symbol)> = attributes.iter()
+            .map(|(key, value)| (self.intern(key), self.intern(value)))
+            .collect();
+        pairs.sort();
+        InternedAttributes { pairs }
+    }
+}
+
+// An attribute map made of interned symbols, kept sorted by key. Used as a node
+// or edge payload in place of a HashMap<String, String>, usually wrapped in an
+// Option as the default Attributes payload is.
+#[derive(Clone,Debug,PartialEq,Eq,Default)]
+pub struct InternedAttributes {
+    pairs: Vec<(Symbol, Symbol)>,
+}
+
+impl InternedAttributes {
+    pub fn get(&self, key: &str) -> Option<&Symbol> {
+        match self.pairs.binary_search_by(|(candidate, _)| candidate.as_str().cmp(key)) {
+            Ok(position) => Some(&self.pairs[position].1),
+            Err(_) => None,
+        }
+    }
+
+    pub fn pairs(&self) -> &[(Symbol, Symbol)] {
+        &self.pairs
+    }
+
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    fn set<F: FnMut(&str) -> Symbol>(&mut self, key: &str, value: &str, mut symbol: F) -> Option<String> {
+        match self.pairs.binary_search_by(|(candidate, _)| candidate.as_str().cmp(key)) {
+            Ok(position) => {
+                let previous = self.pairs[position].1.to_string();
+                self.pairs[position].1 = symbol(value);
+                Some(previous)
+            }
+            Err(position) => {
+                self.pairs.insert(position, (symbol(key), symbol(value)));
+                None
+            }
+        }
+    }
+
+    // Both lists being sorted by key, one walk through them finds each
+    // required key. The values found are passed to value_matches.
+    fn matches_sorted<K, V, F>(&self, required: &[(K, V)], value_matches: F) -> bool
+        where K: Deref<Target = str>, F: Fn(&V, &Symbol) -> bool {
+        let mut pairs = self.pairs.iter().peekable();
+        required.iter().all(|(key, value)| {
+            while pairs.peek().is_some_and(|(candidate, _)| candidate.as_str() < &**key) {
+                pairs.next();
+            }
+            match pairs.peek() {
+                Some((candidate, other_value)) if candidate.as_str() == &**key => value_matches(value, other_value),
+                _ => false,
+            }
+        })
+    }
+}
+
+// Symbols from one table are equal when they share a string, which is checked
+// before the text. A query interned in the table of the graph it searches,
+// see Graph::intern_attributes, matches its values that way.
+fn symbols_match(query: &Symbol, value: &Symbol, equality: &EqualityRequirement) -> bool {
+    match *equality {
+        EqualityRequirement::Complete if Symbol::ptr_eq(query, value) => true,
+        _ => matching::value_matches(query, value, equality),
+    }
+}
+
+// every query attribute must be present on the other side with a matching value,
+// as for HashMap attributes
+impl Matches for InternedAttributes {
+    fn matches(&self, other: &InternedAttributes, equality: &EqualityRequirement) -> bool {
+        other.matches_sorted(&self.pairs, |value, other_value| symbols_match(value, other_value, equality))
+    }
+
+    fn required_attributes(&self) -> Vec<(String, String)> {
+        self.attribute_pairs()
+    }
+
+    // the query's own pairs are sorted symbols already
+    fn matches_compiled(&self, _requirements: &Requirements, other: &InternedAttributes, equality: &EqualityRequirement) -> bool {
+        self.matches(other, equality)
+    }
+
+    fn single_valued() -> bool {
+        true
+    }
+}
+
+// so that queries written with plain maps can search interned graphs
+impl Matches<InternedAttributes> for HashMap<String, String> {
+    fn matches(&self, other: &InternedAttributes, equality: &EqualityRequirement) -> bool {
+        self.iter().all(|(key, value)| {
+            match other.get(key) {
+                Some(other_value) => matching::value_matches(value, other_value, equality),
+                None => false,
+            }
+        })
+    }
+
+    fn required_attributes(&self) -> Vec<(String, String)> {
+        self.attribute_pairs()
+    }
+
+    fn matches_compiled(&self, requirements: &Requirements, other: &InternedAttributes, equality: &EqualityRequirement) -> bool {
+        other.matches_sorted(requirements.pairs(), |value, other_value| matching::value_matches(value, other_value, equality))
+    }
+
+    fn single_valued() -> bool {
+        true
+    }
+}
+
+// Without a table set_attribute can only make new symbols, which are not
+// interned. Graph::set_node_attribute and set_edge_attribute intern through the
+// graph's table when it keeps one.
+impl AttributesMut for InternedAttributes {
+    fn set_attribute(&mut self, key: &str, value: &str) -> Option<String> {
+        self.set(key, value, |string| Symbol::from(string))
+    }
+
+    fn set_attribute_interned(&mut self, key: &str, value: &str, symbols: &mut SymbolTable) -> Option<String> {
+        self.set(key, value, |string| symbols.intern(string))
+    }
+
+    fn remove_attribute(&mut self, key: &str) -> Option<String> {
+        match self.pairs.binary_search_by(|(candidate, _)| candidate.as_str().cmp(key)) {
+            Ok(position) => Some(self.pairs.remove(position).1.to_string()),
+            Err(_) => None,
+        }
+    }
+}
+
+impl AttributePairs for InternedAttributes {
+    fn attribute_pairs(&self) -> Vec<(String, String)> {
+        self.pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    fn attribute(&self, key: &str) -> Option<String> {
+        self.get(key).map(|value| value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|&(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn interning() {
+        let mut table = SymbolTable::new();
+        let a = table.intern("NN");
+        let b = table.intern("NN");
+        assert!(Symbol::ptr_eq(&a, &b));
+        assert_eq!(1, table.len());
+
+        let c = Symbol::from("NN");
+        assert!(!Symbol::ptr_eq(&a, &c));
+        assert_eq!(a, c);
+        assert_eq!("NN", a);
+        assert_eq!(a, "NN".to_string());
+    }
+
+    #[test]
+    fn interned_matching() {
+        let mut table = SymbolTable::new();
+        let graph_attributes = table.intern_attributes(&attributes(&[("pos", "NNS"), ("lemma", "dog")]));
+        let query = table.intern_attributes(&attributes(&[("pos", "NNS")]));
+        let partial = table.intern_attributes(&attributes(&[("pos", "NN")]));
+        assert_eq!(Some(&Symbol::from("dog")), graph_attributes.get("lemma"));
+        assert!(query.matches(&graph_attributes, &EqualityRequirement::Complete));
+        assert!(!partial.matches(&graph_attributes, &EqualityRequirement::Complete));
+        assert!(partial.matches(&graph_attributes, &EqualityRequirement::Contains));
+        assert!(attributes(&[("lemma", "dog")]).matches(&graph_attributes, &EqualityRequirement::Complete));
+        assert!(!attributes(&[("lemma", "cat")]).matches(&graph_attributes, &EqualityRequirement::Complete));
+        assert_eq!(5, table.len());
+        // values interned in one table share their strings, others are
+        // compared by text
+        assert!(Symbol::ptr_eq(&query.pairs()[0].1, graph_attributes.get("pos").unwrap()));
+        let mut other_table = SymbolTable::new();
+        let foreign = other_table.intern_attributes(&attributes(&[("pos", "NNS")]));
+        assert!(!Symbol::ptr_eq(&foreign.pairs()[0].1, graph_attributes.get("pos").unwrap()));
+        assert!(foreign.matches(&graph_attributes, &EqualityRequirement::Complete));
+        let requirements = Requirements::new(query.required_attributes());
+        assert!(query.matches_compiled(&requirements, &graph_attributes, &EqualityRequirement::Complete));
+        let requirements = Requirements::new(partial.required_attributes());
+        assert!(!partial.matches_compiled(&requirements, &graph_attributes, &EqualityRequirement::Complete));
+        assert!(partial.matches_compiled(&requirements, &graph_attributes, &EqualityRequirement::Contains));
+
+        let mut changed = graph_attributes.clone();
+        assert_eq!(Some("NNS".to_string()), changed.set_attribute("pos", "VB"));
+        assert_eq!(None, changed.set_attribute("case", "nom"));
+        assert_eq!(Some(&Symbol::from("nom")), changed.get("case"));
+        assert_eq!(Some("dog".to_string()), changed.remove_attribute("lemma"));
+        assert!(!query.matches(&changed, &EqualityRequirement::Complete));
+        assert_eq!(2, changed.len());
+
+        let length = table.len();
+        assert_eq!(Some("VB".to_string()), changed.set_attribute_interned("pos", "NNS", &mut table));
+        assert_eq!(None, changed.set_attribute_interned("number", "plural", &mut table));
+        assert!(Symbol::ptr_eq(&table.intern("NNS"), changed.get("pos").unwrap()));
+        assert_eq!(length + 2, table.len());
+    }
+}
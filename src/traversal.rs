@@ -0,0 +1,312 @@
+use std::collections::VecDeque;
+use node;
+use edge;
+use error::{Error, unwrap};
+use expansion::EdgeFilter;
+use view::GraphView;
+
+// Iterators over the nodes of a graph, or of a frozen graph. Each walker visits
+// a node at most once and follows a node's outgoing edges in the graph's order
+// for them. An edge filter restricts which edges are followed.
+
+fn followed_edges<G: GraphView>(graph: &G, filter: &Option<EdgeFilter<G::E>>, node_index: node::Index)
+    -> Vec<edge::Index> {
+        match *filter {
+            Some(ref filter) => graph.outgoing(node_index).filter(|&edge_index| filter(&graph.edges()[edge_index])).collect(),
+            None => graph.outgoing(node_index).collect(),
+        }
+    }
+
+pub struct Bfs<'a, G: GraphView + 'a> {
+    graph: &'a G,
+    filter: Option<EdgeFilter<'a, G::E>>,
+    queue: VecDeque<node::Index>,
+    discovered: Vec<bool>,
+}
+
+impl<'a, G: GraphView> Bfs<'a, G> {
+    pub fn new(graph: &'a G, root_index: node::Index) -> Bfs<'a, G> {
+        unwrap(Bfs::try_new(graph, root_index))
+    }
+
+    pub fn try_new(graph: &'a G, root_index: node::Index) -> Result<Bfs<'a, G>, Error> {
+        graph.check_node(root_index)?;
+        let mut discovered = vec![false; graph.nodes().len()];
+        discovered[root_index] = true;
+        Ok(Bfs { graph, filter: None, queue: vec![root_index].into_iter().collect(), discovered })
+    }
+
+    pub fn edge_filter<F>(mut self, filter: F) -> Bfs<'a, G>
+        where F: Fn(&edge::Edge<G::E>) -> bool + 'a {
+            self.filter = Some(Box::new(filter));
+            self
+        }
+}
+
+impl<'a, G: GraphView> Iterator for Bfs<'a, G> {
+    type Item = node::Index;
+
+    fn next(&mut self) -> Option<node::Index> {
+        let node_index = self.queue.pop_front()?;
+        for edge_index in followed_edges(self.graph, &self.filter, node_index) {
+            let target = self.graph.edges()[edge_index].target;
+            if !self.discovered[target] {
+                self.discovered[target] = true;
+                self.queue.push_back(target);
+            }
+        }
+        Some(node_index)
+    }
+}
+
+// Depth first, yielding each node before any of its descendants.
+pub struct Dfs<'a, G: GraphView + 'a> {
+    graph: &'a G,
+    filter: Option<EdgeFilter<'a, G::E>>,
+    stack: Vec<node::Index>,
+    visited: Vec<bool>,
+}
+
+impl<'a, G: GraphView> Dfs<'a, G> {
+    pub fn new(graph: &'a G, root_index: node::Index) -> Dfs<'a, G> {
+        unwrap(Dfs::try_new(graph, root_index))
+    }
+
+    pub fn try_new(graph: &'a G, root_index: node::Index) -> Result<Dfs<'a, G>, Error> {
+        graph.check_node(root_index)?;
+        Ok(Dfs { graph, filter: None, stack: vec![root_index], visited: vec![false; graph.nodes().len()] })
+    }
+
+    pub fn edge_filter<F>(mut self, filter: F) -> Dfs<'a, G>
+        where F: Fn(&edge::Edge<G::E>) -> bool + 'a {
+            self.filter = Some(Box::new(filter));
+            self
+        }
+}
+
+impl<'a, G: GraphView> Iterator for Dfs<'a, G> {
+    type Item = node::Index;
+
+    fn next(&mut self) -> Option<node::Index> {
+        while let Some(node_index) = self.stack.pop() {
+            if self.visited[node_index] {
+                continue;
+            }
+            self.visited[node_index] = true;
+            for edge_index in followed_edges(self.graph, &self.filter, node_index).into_iter().rev() {
+                let target = self.graph.edges()[edge_index].target;
+                if !self.visited[target] {
+                    self.stack.push(target);
+                }
+            }
+            return Some(node_index);
+        }
+        None
+    }
+}
+
+// Depth first, yielding each node after all of its descendants.
+pub struct DfsPostOrder<'a, G: GraphView + 'a> {
+    graph: &'a G,
+    filter: Option<EdgeFilter<'a, G::E>>,
+    // each entry is a node and the outgoing edges still to be explored from it
+    stack: Vec<(node::Index, Vec<edge::Index>)>,
+    discovered: Vec<bool>,
+    root_index: Option<node::Index>,
+}
+
+impl<'a, G: GraphView> DfsPostOrder<'a, G> {
+    pub fn new(graph: &'a G, root_index: node::Index) -> DfsPostOrder<'a, G> {
+        unwrap(DfsPostOrder::try_new(graph, root_index))
+    }
+
+    pub fn try_new(graph: &'a G, root_index: node::Index) -> Result<DfsPostOrder<'a, G>, Error> {
+        graph.check_node(root_index)?;
+        Ok(DfsPostOrder {
+            graph,
+            filter: None,
+            stack: vec![],
+            discovered: vec![false; graph.nodes().len()],
+            root_index: Some(root_index),
+        })
+    }
+
+    pub fn edge_filter<F>(mut self, filter: F) -> DfsPostOrder<'a, G>
+        where F: Fn(&edge::Edge<G::E>) -> bool + 'a {
+            self.filter = Some(Box::new(filter));
+            self
+        }
+
+    // the filter may be set after construction, so the root is only expanded
+    // once iteration starts
+    fn discover(&mut self, node_index: node::Index) {
+        self.discovered[node_index] = true;
+        let mut edges = followed_edges(self.graph, &self.filter, node_index);
+        edges.reverse();
+        self.stack.push((node_index, edges));
+    }
+}
+
+impl<'a, G: GraphView> Iterator for DfsPostOrder<'a, G> {
+    type Item = node::Index;
+
+    fn next(&mut self) -> Option<node::Index> {
+        if let Some(root_index) = self.root_index.take() {
+            self.discover(root_index);
+        }
+        loop {
+            let next_target = {
+                let &mut (_, ref mut edges) = self.stack.last_mut()?;
+                match edges.pop() {
+                    Some(edge_index) => Some(self.graph.edges()[edge_index].target),
+                    None => None,
+                }
+            };
+            match next_target {
+                Some(target) => {
+                    if !self.discovered[target] {
+                        self.discover(target);
+                    }
+                }
+                None => return self.stack.pop().map(|(node_index, _)| node_index),
+            }
+        }
+    }
+}
+
+// Every node in the graph ordered so that each comes before the targets of its
+// outgoing edges. Nodes that lie on or behind a cycle can never be placed and
+// are not yielded, is_complete reports whether any were left out.
+pub struct Topological<'a, G: GraphView + 'a> {
+    graph: &'a G,
+    filter: Option<EdgeFilter<'a, G::E>>,
+    in_degrees: Option<Vec<usize>>,
+    queue: VecDeque<node::Index>,
+    yielded: usize,
+}
+
+impl<'a, G: GraphView> Topological<'a, G> {
+    pub fn new(graph: &'a G) -> Topological<'a, G> {
+        Topological { graph, filter: None, in_degrees: None, queue: VecDeque::new(), yielded: 0 }
+    }
+
+    pub fn edge_filter<F>(mut self, filter: F) -> Topological<'a, G>
+        where F: Fn(&edge::Edge<G::E>) -> bool + 'a {
+            self.filter = Some(Box::new(filter));
+            self
+        }
+
+    pub fn is_complete(&self) -> bool {
+        self.in_degrees.is_some() && self.queue.is_empty() && self.yielded == self.graph.node_count()
+    }
+
+    fn start(&mut self) {
+        let mut in_degrees = vec![0; self.graph.nodes().len()];
+        for node_index in self.graph.node_indices() {
+            for edge_index in followed_edges(self.graph, &self.filter, node_index) {
+                in_degrees[self.graph.edges()[edge_index].target] += 1;
+            }
+        }
+        for node_index in self.graph.node_indices() {
+            if in_degrees[node_index] == 0 {
+                self.queue.push_back(node_index);
+            }
+        }
+        self.in_degrees = Some(in_degrees);
+    }
+}
+
+impl<'a, G: GraphView> Iterator for Topological<'a, G> {
+    type Item = node::Index;
+
+    fn next(&mut self) -> Option<node::Index> {
+        if self.in_degrees.is_none() {
+            self.start();
+        }
+        let node_index = self.queue.pop_front()?;
+        for edge_index in followed_edges(self.graph, &self.filter, node_index) {
+            let target = self.graph.edges()[edge_index].target;
+            if let Some(ref mut in_degrees) = self.in_degrees {
+                in_degrees[target] -= 1;
+                if in_degrees[target] == 0 {
+                    self.queue.push_back(target);
+                }
+            }
+        }
+        self.yielded += 1;
+        Some(node_index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::Graph;
+    use super::*;
+
+    //      0
+    //     / \
+    //    1   2
+    //   / \ /
+    //  3   4
+    fn tree() -> Graph {
+        let mut graph = Graph::new();
+        for n in 0..5 {
+            graph.add_node(format!("node{}", n), None);
+        }
+        graph.add_edge(0, 1, "left".to_string(), None);
+        graph.add_edge(0, 2, "right".to_string(), None);
+        graph.add_edge(1, 3, "left".to_string(), None);
+        graph.add_edge(1, 4, "right".to_string(), None);
+        graph.add_edge(2, 4, "left".to_string(), None);
+        graph
+    }
+
+    #[test]
+    fn breadth_first() {
+        let graph = tree();
+        assert_eq!(vec![0, 1, 2, 3, 4], Bfs::new(&graph, 0).collect::<Vec<_>>());
+        assert_eq!(vec![2, 4], Bfs::new(&graph, 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn depth_first() {
+        let graph = tree();
+        assert_eq!(vec![0, 1, 3, 4, 2], Dfs::new(&graph, 0).collect::<Vec<_>>());
+        assert_eq!(vec![3, 4, 1, 2, 0], DfsPostOrder::new(&graph, 0).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn edge_filters() {
+        let graph = tree();
+        let left = |edge: &edge::Edge| edge.identifier == "left";
+        assert_eq!(vec![0, 1, 3], Bfs::new(&graph, 0).edge_filter(left).collect::<Vec<_>>());
+        assert_eq!(vec![0, 1, 3], Dfs::new(&graph, 0).edge_filter(left).collect::<Vec<_>>());
+        assert_eq!(vec![3, 1, 0], DfsPostOrder::new(&graph, 0).edge_filter(left).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn topological() {
+        let mut graph = tree();
+        let order: Vec<_> = Topological::new(&graph).collect();
+        assert_eq!(vec![0, 1, 2, 3, 4], order);
+
+        graph.add_edge(4, 1, "cycle".to_string(), None);
+        let mut walker = Topological::new(&graph);
+        assert_eq!(vec![0, 2], walker.by_ref().collect::<Vec<_>>());
+        assert!(!walker.is_complete());
+
+        let mut walker = Topological::new(&graph).edge_filter(|edge: &edge::Edge| edge.identifier != "cycle");
+        assert_eq!(5, walker.by_ref().count());
+        assert!(walker.is_complete());
+    }
+
+    #[test]
+    fn cycles_and_invalid_roots() {
+        let mut graph = tree();
+        graph.add_edge(4, 0, "cycle".to_string(), None);
+        assert_eq!(5, Dfs::new(&graph, 4).count());
+        assert_eq!(5, DfsPostOrder::new(&graph, 4).count());
+        assert_eq!(5, Bfs::new(&graph, 4).count());
+        assert!(Bfs::try_new(&graph, 9).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use graph;
+use node;
+use edge;
+use error::{Error, unwrap};
+
+// Precomputed queries over a graph that forms a rooted tree or a forest of
+// them, as dependency parses do. The index borrows the graph it was built
+// from, so the graph cannot be changed while the index is alive and a new
+// index must be built after any mutation.
+//
+// Building the index takes O(n log n) time for a graph of n nodes. Every query
+// then takes constant time: each checks that its nodes are in the graph, an
+// indexed lookup, and lowest common ancestors are read from a range minimum
+// table over the Euler tour of each tree.
+pub struct TreeIndex<'g, N: 'g, E: 'g> {
+    graph: &'g graph::Graph<N, E>,
+    roots: Vec<node::Index>,
+    parents: Vec<Option<(edge::Index, node::Index)>>,
+    tree_roots: Vec<node::Index>,
+    depths: Vec<usize>,
+    sizes: Vec<usize>,
+    euler: Vec<node::Index>,
+    first_visits: Vec<usize>,
+    last_visits: Vec<usize>,
+    // minimums[k][i] is the position in euler of the shallowest node in
+    // euler[i..i + 2^k]
+    minimums: Vec<Vec<usize>>,
+}
+
+impl<'g, N, E> TreeIndex<'g, N, E> {
+    // Fails with Error::NotATree naming a node with more than one incoming
+    // edge, or one that sits on a cycle and so cannot be reached from a root.
+    pub fn new(graph: &'g graph::Graph<N, E>) -> Result<TreeIndex<'g, N, E>, Error> {
+        let count = graph.nodes.len();
+        let mut parents: Vec<Option<(edge::Index, node::Index)>> = vec![None; count];
+        for node_index in graph.node_indices() {
+            for edge_index in graph.edges_for_node(node_index) {
+                let target = graph.edges[edge_index].target;
+                if parents[target].is_some() || target == node_index {
+                    return Err(Error::NotATree(target));
+                }
+                parents[target] = Some((edge_index, node_index));
+            }
+        }
+        let roots: Vec<node::Index> = graph.node_indices().into_iter()
+            .filter(|&node_index| parents[node_index].is_none())
+            .collect();
+
+        let mut index = TreeIndex {
+            graph,
+            roots: vec![],
+            parents,
+            tree_roots: vec![0; count],
+            depths: vec![0; count],
+            sizes: vec![0; count],
+            euler: vec![],
+            first_visits: vec![0; count],
+            last_visits: vec![0; count],
+            minimums: vec![],
+        };
+        let mut visited = 0;
+        for &root in &roots {
+            visited += index.tour(root);
+        }
+        if visited != graph.node_count() {
+            let unreached = graph.node_indices().into_iter()
+                .find(|&node_index| index.sizes[node_index] == 0)
+                .expect("a node was not reached");
+            return Err(Error::NotATree(unreached));
+        }
+        index.roots = roots;
+        index.build_minimums();
+        Ok(index)
+    }
+
+    // Walks the tree under root, recording the Euler tour, depths and subtree
+    // sizes. Returns the number of nodes in the tree.
+    fn tour(&mut self, root: node::Index) -> usize {
+        let mut stack: Vec<(node::Index, Vec<edge::Index>)> = vec![];
+        let mut visited = 0;
+        self.enter(&mut stack, root, root);
+
+        while let Some(&mut (_, ref mut edges)) = stack.last_mut() {
+            match edges.pop() {
+                Some(edge_index) => {
+                    let target = self.graph.edges[edge_index].target;
+                    self.enter(&mut stack, target, root);
+                }
+                None => {
+                    let (node_index, _) = stack.pop().expect("stack is not empty");
+                    visited += 1;
+                    self.sizes[node_index] += 1;
+                    self.last_visits[node_index] = self.euler.len() - 1;
+                    if let Some(&(parent, _)) = stack.last() {
+                        self.sizes[parent] += self.sizes[node_index];
+                        self.euler.push(parent);
+                    }
+                }
+            }
+        }
+        visited
+    }
+
+    fn enter(&mut self, stack: &mut Vec<(node::Index, Vec<edge::Index>)>, node_index: node::Index, root: node::Index) {
+        self.tree_roots[node_index] = root;
+        self.depths[node_index] = stack.len();
+        self.first_visits[node_index] = self.euler.len();
+        self.euler.push(node_index);
+        let mut edges = self.graph.edges_for_node(node_index);
+        edges.reverse();
+        stack.push((node_index, edges));
+    }
+
+    fn shallower(&self, a: usize, b: usize) -> usize {
+        if self.depths[self.euler[b]] < self.depths[self.euler[a]] { b } else { a }
+    }
+
+    fn build_minimums(&mut self) {
+        let mut minimums = vec![(0..self.euler.len()).collect::<Vec<usize>>()];
+        let mut width = 1;
+        while width * 2 <= self.euler.len() {
+            let next = {
+                let previous = minimums.last().expect("first level is always present");
+                (0..self.euler.len() + 1 - width * 2)
+                    .map(|i| self.shallower(previous[i], previous[i + width]))
+                    .collect()
+            };
+            minimums.push(next);
+            width *= 2;
+        }
+        self.minimums = minimums;
+    }
+
+    pub fn roots(&self) -> &[node::Index] {
+        &self.roots
+    }
+
+    // The queries below panic when a node is not in the graph, each has a try_
+    // counterpart returning Error::InvalidNode instead.
+    pub fn parent(&self, node_index: node::Index) -> Option<node::Index> {
+        unwrap(self.try_parent(node_index))
+    }
+
+    pub fn try_parent(&self, node_index: node::Index) -> Result<Option<node::Index>, Error> {
+        self.graph.check_node(node_index)?;
+        Ok(self.parents[node_index].map(|(_, parent)| parent))
+    }
+
+    pub fn parent_edge(&self, node_index: node::Index) -> Option<edge::Index> {
+        unwrap(self.try_parent_edge(node_index))
+    }
+
+    pub fn try_parent_edge(&self, node_index: node::Index) -> Result<Option<edge::Index>, Error> {
+        self.graph.check_node(node_index)?;
+        Ok(self.parents[node_index].map(|(edge_index, _)| edge_index))
+    }
+
+    pub fn root_of(&self, node_index: node::Index) -> node::Index {
+        unwrap(self.try_root_of(node_index))
+    }
+
+    pub fn try_root_of(&self, node_index: node::Index) -> Result<node::Index, Error> {
+        self.graph.check_node(node_index)?;
+        Ok(self.tree_roots[node_index])
+    }
+
+    // Roots are at depth 0.
+    pub fn depth(&self, node_index: node::Index) -> usize {
+        unwrap(self.try_depth(node_index))
+    }
+
+    pub fn try_depth(&self, node_index: node::Index) -> Result<usize, Error> {
+        self.graph.check_node(node_index)?;
+        Ok(self.depths[node_index])
+    }
+
+    // The number of nodes in the subtree under a node, including the node itself.
+    pub fn subtree_size(&self, node_index: node::Index) -> usize {
+        unwrap(self.try_subtree_size(node_index))
+    }
+
+    pub fn try_subtree_size(&self, node_index: node::Index) -> Result<usize, Error> {
+        self.graph.check_node(node_index)?;
+        Ok(self.sizes[node_index])
+    }
+
+    // Every node counts as its own ancestor.
+    pub fn is_ancestor(&self, ancestor: node::Index, node_index: node::Index) -> bool {
+        unwrap(self.try_is_ancestor(ancestor, node_index))
+    }
+
+    pub fn try_is_ancestor(&self, ancestor: node::Index, node_index: node::Index) -> Result<bool, Error> {
+        self.graph.check_node(ancestor)?;
+        self.graph.check_node(node_index)?;
+        Ok(self.tree_roots[ancestor] == self.tree_roots[node_index] &&
+            self.first_visits[ancestor] <= self.first_visits[node_index] &&
+            self.first_visits[node_index] <= self.last_visits[ancestor])
+    }
+
+    // None when the nodes are in different trees.
+    pub fn lowest_common_ancestor(&self, a: node::Index, b: node::Index) -> Option<node::Index> {
+        unwrap(self.try_lowest_common_ancestor(a, b))
+    }
+
+    pub fn try_lowest_common_ancestor(&self, a: node::Index, b: node::Index) -> Result<Option<node::Index>, Error> {
+        self.graph.check_node(a)?;
+        self.graph.check_node(b)?;
+        if self.tree_roots[a] != self.tree_roots[b] {
+            return Ok(None);
+        }
+        let (start, end) = if self.first_visits[a] <= self.first_visits[b] {
+            (self.first_visits[a], self.first_visits[b])
+        } else {
+            (self.first_visits[b], self.first_visits[a])
+        };
+        // the largest power of two no wider than the range
+        let level = (usize::BITS - 1 - (end - start + 1).leading_zeros()) as usize;
+        let position = self.shallower(self.minimums[level][start], self.minimums[level][end + 1 - (1 << level)]);
+        Ok(Some(self.euler[position]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use graph::Graph;
+    use super::*;
+
+    //      0         5
+    //     / \        |
+    //    1   2       6
+    //   / \
+    //  3   4
+    fn forest() -> Graph {
+        let mut graph = Graph::new();
+        for n in 0..7 {
+            graph.add_node(format!("node{}", n), None);
+        }
+        graph.add_edge(0, 1, "edge".to_string(), None);
+        graph.add_edge(0, 2, "edge".to_string(), None);
+        graph.add_edge(1, 3, "edge".to_string(), None);
+        graph.add_edge(1, 4, "edge".to_string(), None);
+        graph.add_edge(5, 6, "edge".to_string(), None);
+        graph
+    }
+
+    #[test]
+    fn depths_and_sizes() {
+        let graph = forest();
+        let index = TreeIndex::new(&graph).unwrap();
+        assert_eq!(&[0, 5], index.roots());
+        assert_eq!(2, index.depth(4));
+        assert_eq!(0, index.depth(5));
+        assert_eq!(5, index.subtree_size(0));
+        assert_eq!(3, index.subtree_size(1));
+        assert_eq!(1, index.subtree_size(6));
+        assert_eq!(Some(1), index.parent(3));
+        assert_eq!(Some(2), index.parent_edge(3));
+        assert_eq!(None, index.parent(0));
+        assert_eq!(0, index.root_of(4));
+    }
+
+    #[test]
+    fn ancestors() {
+        let graph = forest();
+        let index = TreeIndex::new(&graph).unwrap();
+        assert!(index.is_ancestor(0, 4));
+        assert!(index.is_ancestor(1, 1));
+        assert!(!index.is_ancestor(2, 4));
+        assert!(!index.is_ancestor(4, 1));
+        assert!(!index.is_ancestor(5, 4));
+
+        assert_eq!(Some(1), index.lowest_common_ancestor(3, 4));
+        assert_eq!(Some(0), index.lowest_common_ancestor(3, 2));
+        assert_eq!(Some(1), index.lowest_common_ancestor(1, 4));
+        assert_eq!(Some(6), index.lowest_common_ancestor(6, 6));
+        assert_eq!(None, index.lowest_common_ancestor(4, 6));
+    }
+
+    #[test]
+    fn invalid_nodes() {
+        let mut graph = forest();
+        graph.remove_node(6);
+        let index = TreeIndex::new(&graph).unwrap();
+        assert_eq!(Err(Error::InvalidNode(6)), index.try_depth(6));
+        assert_eq!(Err(Error::InvalidNode(7)), index.try_parent(7));
+        assert_eq!(Err(Error::InvalidNode(9)), index.try_is_ancestor(0, 9));
+        assert_eq!(Err(Error::InvalidNode(6)), index.try_lowest_common_ancestor(6, 0));
+        assert_eq!(Ok(Some(0)), index.try_lowest_common_ancestor(3, 2));
+    }
+
+    #[test]
+    fn not_a_tree() {
+        let mut graph = forest();
+        graph.add_edge(2, 4, "edge".to_string(), None);
+        assert_eq!(Some(Error::NotATree(4)), TreeIndex::new(&graph).err());
+
+        let mut graph = forest();
+        graph.add_edge(6, 5, "edge".to_string(), None);
+        assert_eq!(Some(Error::NotATree(5)), TreeIndex::new(&graph).err());
+    }
+
+    #[test]
+    fn removed_nodes() {
+        let mut graph = forest();
+        graph.remove_node(1);
+        let index = TreeIndex::new(&graph).unwrap();
+        assert_eq!(&[0, 3, 4, 5], index.roots());
+        assert_eq!(2, index.subtree_size(0));
+    }
+}
@@ -0,0 +1,107 @@
+use std::slice;
+use node;
+use edge;
+use error::Error;
+
+// Read only access to a graph, shared by graph::Graph and the frozen
+// frozen::FrozenGraph so that matching and traversals can run on either. Node
+// and edge indexes mean the same in both, including removed entries.
+pub trait GraphView {
+    type N;
+    type E;
+
+    // every node slot, removed nodes included
+    fn nodes(&self) -> &[node::Node<Self::N>];
+
+    fn edges(&self) -> &[edge::Edge<Self::E>];
+
+    // The outgoing edges of a live node, in the graph's order for them.
+    fn outgoing(&self, node_index: node::Index) -> OutgoingEdges<'_, Self::E>;
+
+    // The edges entering a live node, in ascending edge index order.
+    fn incoming(&self, node_index: node::Index) -> IncomingEdges<'_, Self::E>;
+
+    // The nodes having all of the given attribute pairs when the graph keeps
+    // an attribute index, see Graph::indexed_nodes.
+    fn indexed_nodes(&self, pairs: &[(String, String)]) -> Option<Vec<node::Index>>;
+
+    // The length of the shortest attribute index list among the pairs, which
+    // no more nodes than that can have all of, without intersecting the
+    // lists. None as for indexed_nodes.
+    fn indexed_node_bound(&self, pairs: &[(String, String)]) -> Option<usize>;
+
+    // The number of outgoing edges of a live node.
+    fn out_degree(&self, node_index: node::Index) -> usize {
+        self.outgoing(node_index).count()
+    }
+
+    // Whether a live node has at least count outgoing edges, following no
+    // more than count of them.
+    fn has_outgoing(&self, node_index: node::Index, count: usize) -> bool {
+        count == 0 || self.outgoing(node_index).nth(count - 1).is_some()
+    }
+
+    fn contains_node(&self, node_index: node::Index) -> bool {
+        match self.nodes().get(node_index) {
+            Some(node) => !node.removed,
+            None => false,
+        }
+    }
+
+    fn check_node(&self, node_index: node::Index) -> Result<(), Error> {
+        if self.contains_node(node_index) { Ok(()) } else { Err(Error::InvalidNode(node_index)) }
+    }
+
+    fn node_indices(&self) -> Vec<node::Index> {
+        (0..self.nodes().len()).filter(|&index| !self.nodes()[index].removed).collect()
+    }
+
+    fn node_count(&self) -> usize {
+        self.nodes().iter().filter(|node| !node.removed).count()
+    }
+}
+
+pub enum OutgoingEdges<'g, E: 'g> {
+    // following next_outgoing_edge links from a node's first edge
+    Linked { edges: &'g [edge::Edge<E>], next: Option<edge::Index> },
+    // a node's contiguous run of edges in a frozen graph
+    Slice(slice::Iter<'g, edge::Index>),
+}
+
+impl<'g, E> Iterator for OutgoingEdges<'g, E> {
+    type Item = edge::Index;
+
+    fn next(&mut self) -> Option<edge::Index> {
+        match *self {
+            OutgoingEdges::Linked { edges, ref mut next } => {
+                let edge_index = (*next)?;
+                *next = edges[edge_index].next_outgoing_edge;
+                Some(edge_index)
+            }
+            OutgoingEdges::Slice(ref mut edge_indexes) => edge_indexes.next().cloned(),
+        }
+    }
+}
+
+// following next_incoming_edge links from a node's first incoming edge, which
+// graph::Graph and frozen::FrozenGraph both keep
+pub struct IncomingEdges<'g, E: 'g> {
+    edges: &'g [edge::Edge<E>],
+    next: Option<edge::Index>,
+}
+
+impl<'g, E> IncomingEdges<'g, E> {
+    pub(crate) fn new(first: Option<edge::Index>, edges: &'g [edge::Edge<E>]) -> IncomingEdges<'g, E> {
+        IncomingEdges { edges, next: first }
+    }
+}
+
+impl<'g, E> Iterator for IncomingEdges<'g, E> {
+    type Item = edge::Index;
+
+    fn next(&mut self) -> Option<edge::Index> {
+        let edge_index = self.next?;
+        self.next = self.edges[edge_index].next_incoming_edge;
+        Some(edge_index)
+    }
+}
@@ -2,14 +2,20 @@ extern crate graph_match;
 
 use std::collections::HashMap;
 use graph_match::graph;
-use graph_match::matching::EqualityRequirement;
+use graph_match::snapshot;
+use graph_match::expansion::ExpandOptions;
+use graph_match::subgraph::EdgeSelection;
+use graph_match::corpus::CorpusMatches;
+use graph_match::traversal::Bfs;
+use graph_match::matching::{EqualityRequirement, Matches};
+use graph_match::symbols::InternedAttributes;
+use graph_match::rewrite::{self, EdgeRef, Rule, Value};
+use graph_match::diff::{self, NodeMatching};
+use graph_match::canonical;
 
 #[test]
 fn traversal_simple() {
-    let mut simple_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut simple_graph = graph::Graph::new();
     let node0 = simple_graph.add_node("node0".to_string(), None);
     let node1 = simple_graph.add_node("node1".to_string(), None);
     let node2 = simple_graph.add_node("node2".to_string(), None);
@@ -22,10 +28,7 @@ fn traversal_simple() {
 
 #[test]
 fn traveral_incomplete() {
-    let mut simple_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut simple_graph = graph::Graph::new();
     let node0 = simple_graph.add_node("node0".to_string(), None);
     let node1 = simple_graph.add_node("node1".to_string(), None);
     let node2 = simple_graph.add_node("node2".to_string(), None);
@@ -38,10 +41,7 @@ fn traveral_incomplete() {
 
 #[test]
 fn traveral_restricted() {
-    let mut simple_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut simple_graph = graph::Graph::new();
     let node0 = simple_graph.add_node("node0".to_string(), None);
     let node1 = simple_graph.add_node("node1".to_string(), None);
     let node2 = simple_graph.add_node("node2".to_string(), None);
@@ -54,22 +54,37 @@ fn traveral_restricted() {
                graph_match::expand_subgraph(&simple_graph, 0, &vec!["banned".to_string()]));
 }
 
+#[test]
+fn traversal_with_options() {
+    let mut simple_graph = graph::Graph::new();
+    let node0 = simple_graph.add_node("node0".to_string(), None);
+    let node1 = simple_graph.add_node("node1".to_string(), None);
+    let node2 = simple_graph.add_node("node2".to_string(), None);
+    let node3 = simple_graph.add_node("node3".to_string(), None);
+    simple_graph.add_edge(node0, node1, "edge0".to_string(), None);
+    simple_graph.add_edge(node0, node2, "edge1".to_string(), None);
+    simple_graph.add_edge(node1, node3, "edge2".to_string(), None);
+    simple_graph.add_edge(node2, node3, "edge3".to_string(), None);
+    simple_graph.add_edge(node3, node0, "banned".to_string(), None);
+
+    let options = ExpandOptions::new().ban("banned").max_depth(2);
+    assert_eq!(vec![0, 1, 3, 2],
+               graph_match::expand_subgraph_with(&simple_graph, 0, &options).unwrap());
+    let options = ExpandOptions::new().max_depth(1);
+    assert_eq!(vec![3, 0],
+               graph_match::expand_subgraph_with(&simple_graph, 3, &options).unwrap());
+}
+
 #[test]
 fn match_complete_graph() {
-    let mut simple_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut simple_graph = graph::Graph::new();
     let node0 = simple_graph.add_node("node0".to_string(), None);
     let node1 = simple_graph.add_node("node1".to_string(), None);
     let node2 = simple_graph.add_node("node2".to_string(), None);
     simple_graph.add_edge(node0, node1, "edge0".to_string(), None);
     simple_graph.add_edge(node1, node2, "edge1".to_string(), None);
 
-    let mut query_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut query_graph = graph::Graph::new();
     let node0 = query_graph.add_node("node0".to_string(), None);
     let node1 = query_graph.add_node("node1".to_string(), None);
     let node2 = query_graph.add_node("node2".to_string(), None);
@@ -90,10 +105,7 @@ fn match_complete_graph() {
 
 #[test]
 fn match_subgraph() {
-    let mut simple_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut simple_graph = graph::Graph::new();
 
     let mut attributes: HashMap<String,String> = HashMap::new();
     attributes.insert("type".to_string(), "root".to_string());
@@ -106,10 +118,7 @@ fn match_subgraph() {
     simple_graph.add_edge(node1, node2, "edge1".to_string(), None);
     simple_graph.add_edge(node2, node3, "edge1".to_string(), None);
 
-    let mut query_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut query_graph = graph::Graph::new();
     let node0 = query_graph.add_node("node0".to_string(), Some(attributes.clone()));
     let node1 = query_graph.add_node("node1".to_string(), None);
     let node2 = query_graph.add_node("node2".to_string(), None);
@@ -132,19 +141,13 @@ fn match_subgraph() {
 fn match_failure() {
     let mut attributes = HashMap::new();
     attributes.insert("key".to_string(), "value".to_string());
-    let mut simple_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut simple_graph = graph::Graph::new();
     let node0 = simple_graph.add_node("node0".to_string(), Some(attributes.clone()));
     let node1 = simple_graph.add_node("node1".to_string(), Some(attributes.clone()));
     simple_graph.add_edge(node0, node1, "edge0".to_string(), Some(attributes.clone()));
 
     attributes.insert("key2".to_string(), "value2".to_string());
-    let mut query_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut query_graph = graph::Graph::new();
     let node0 = query_graph.add_node("node0".to_string(), Some(attributes.clone()));
     let node1 = query_graph.add_node("node1".to_string(), Some(attributes.clone()));
     query_graph.add_edge(node0, node1, "edge0".to_string(), Some(attributes.clone()));
@@ -157,10 +160,7 @@ fn match_failure() {
 
 #[test]
 fn match_multiple_subgraphs() {
-    let mut simple_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut simple_graph = graph::Graph::new();
     let node0 = simple_graph.add_node("node0".to_string(), None);
     let node1 = simple_graph.add_node("node1".to_string(), None);
     let node2 = simple_graph.add_node("node2".to_string(), None);
@@ -168,10 +168,7 @@ fn match_multiple_subgraphs() {
     simple_graph.add_edge(node0, node1, "edge0".to_string(), None);
     simple_graph.add_edge(node2, node3, "edge1".to_string(), None);
 
-    let mut query_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut query_graph = graph::Graph::new();
     let node0 = query_graph.add_node("node0".to_string(), None);
     let node1 = query_graph.add_node("node1".to_string(), None);
     query_graph.add_edge(node0, node1, "edge0".to_string(), None);
@@ -195,10 +192,7 @@ fn match_multiple_subgraphs() {
 
 #[test]
 fn match_target_subgraph() {
-    let mut simple_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut simple_graph = graph::Graph::new();
     let node0 = simple_graph.add_node("node0".to_string(), None);
     let node1 = simple_graph.add_node("node1".to_string(), None);
     let node2 = simple_graph.add_node("node2".to_string(), None);
@@ -206,10 +200,7 @@ fn match_target_subgraph() {
     simple_graph.add_edge(node0, node1, "edge0".to_string(), None);
     simple_graph.add_edge(node2, node3, "edge1".to_string(), None);
 
-    let mut query_graph = graph::Graph {
-        nodes: vec![],
-        edges: vec![],
-    };
+    let mut query_graph = graph::Graph::new();
     let node0 = query_graph.add_node("node0".to_string(), None);
     let node1 = query_graph.add_node("node1".to_string(), None);
     query_graph.add_edge(node0, node1, "edge0".to_string(), None);
@@ -224,3 +215,319 @@ fn match_target_subgraph() {
     assert_eq!(expected,
                graph_match::match_graph(&query_graph, 0, &simple_graph, Some(2), &EqualityRequirement::Complete));
 }
+
+#[test]
+fn match_snapshot_graph() {
+    let mut simple_graph = graph::Graph::new();
+    let node0 = simple_graph.add_node("node0".to_string(), None);
+    let node1 = simple_graph.add_node("node1".to_string(), None);
+    simple_graph.add_edge(node0, node1, "edge0".to_string(), None);
+
+    let bytes = snapshot::to_bytes(&simple_graph);
+    let loaded_graph = snapshot::read(&mut &bytes[..]).unwrap();
+
+    let mut query_graph = graph::Graph::new();
+    let node0 = query_graph.add_node("node0".to_string(), None);
+    let node1 = query_graph.add_node("node1".to_string(), None);
+    query_graph.add_edge(node0, node1, "edge0".to_string(), None);
+
+    assert_eq!(graph_match::match_graph(&query_graph, 0, &simple_graph, None, &EqualityRequirement::Complete),
+               graph_match::match_graph(&query_graph, 0, &loaded_graph, None, &EqualityRequirement::Complete));
+}
+
+#[test]
+fn match_after_removal() {
+    let mut simple_graph = graph::Graph::new();
+    let node0 = simple_graph.add_node("node0".to_string(), None);
+    let node1 = simple_graph.add_node("node1".to_string(), None);
+    let node2 = simple_graph.add_node("node2".to_string(), None);
+    let node3 = simple_graph.add_node("node3".to_string(), None);
+    simple_graph.add_edge(node0, node1, "edge0".to_string(), None);
+    simple_graph.add_edge(node2, node3, "edge1".to_string(), None);
+    simple_graph.remove_node(node1);
+
+    let mut query_graph = graph::Graph::new();
+    let node0 = query_graph.add_node("node0".to_string(), None);
+    let node1 = query_graph.add_node("node1".to_string(), None);
+    query_graph.add_edge(node0, node1, "edge0".to_string(), None);
+
+    let expected = vec![graph_match::matching::MatchedComponents {
+                            list: vec![
+                                graph_match::matching::Component { from_edge: None, node: 2},
+                                graph_match::matching::Component { from_edge: Some(1), node: 3},
+                            ],
+                        }];
+
+    assert_eq!(expected,
+               graph_match::match_graph(&query_graph, 0, &simple_graph, None, &EqualityRequirement::Complete));
+}
+
+#[test]
+fn match_invalid_roots() {
+    let mut simple_graph = graph::Graph::new();
+    simple_graph.add_node("node0".to_string(), None);
+
+    let mut query_graph = graph::Graph::new();
+    query_graph.add_node("node0".to_string(), None);
+
+    assert_eq!(Err(graph_match::Error::InvalidQueryRoot(3)),
+               graph_match::try_match_graph(&query_graph, 3, &simple_graph, None, &EqualityRequirement::Complete));
+    assert_eq!(Err(graph_match::Error::InvalidNode(1)),
+               graph_match::try_match_graph(&query_graph, 0, &simple_graph, Some(1), &EqualityRequirement::Complete));
+    assert_eq!(Err(graph_match::Error::InvalidNode(2)),
+               graph_match::try_expand_subgraph(&simple_graph, 2, &vec![]));
+}
+
+struct Token {
+    pos: &'static str,
+    lemma: &'static str,
+}
+
+struct TokenQuery {
+    pos: Option<&'static str>,
+}
+
+impl Matches<Token> for TokenQuery {
+    fn matches(&self, token: &Token, _equality: &EqualityRequirement) -> bool {
+        match self.pos {
+            Some(pos) => pos == token.pos,
+            None => true,
+        }
+    }
+}
+
+#[test]
+fn match_custom_payloads() {
+    let mut token_graph: graph::Graph<Token, ()> = graph::Graph::default();
+    let node0 = token_graph.add_node("node0".to_string(), Token { pos: "VB", lemma: "eat" });
+    let node1 = token_graph.add_node("node1".to_string(), Token { pos: "NN", lemma: "apple" });
+    let node2 = token_graph.add_node("node2".to_string(), Token { pos: "DT", lemma: "the" });
+    token_graph.add_edge(node0, node1, "dobj".to_string(), ());
+    token_graph.add_edge(node1, node2, "det".to_string(), ());
+
+    let mut query_graph: graph::Graph<TokenQuery, ()> = graph::Graph::default();
+    let node0 = query_graph.add_node("verb".to_string(), TokenQuery { pos: Some("VB") });
+    let node1 = query_graph.add_node("object".to_string(), TokenQuery { pos: None });
+    query_graph.add_edge(node0, node1, "dobj".to_string(), ());
+
+    let matches = graph_match::match_graph(&query_graph, 0, &token_graph, None, &EqualityRequirement::Complete);
+    assert_eq!(1, matches.len());
+    assert_eq!("apple", token_graph.nodes[matches[0].list[1].node].attributes.lemma);
+}
+
+#[test]
+fn match_extracted_subgraph() {
+    let mut simple_graph = graph::Graph::new();
+    let node0 = simple_graph.add_node("node0".to_string(), None);
+    let node1 = simple_graph.add_node("node1".to_string(), None);
+    let node2 = simple_graph.add_node("node2".to_string(), None);
+    let node3 = simple_graph.add_node("node3".to_string(), None);
+    simple_graph.add_edge(node0, node1, "edge0".to_string(), None);
+    simple_graph.add_edge(node1, node3, "banned".to_string(), None);
+    simple_graph.add_edge(node1, node2, "edge1".to_string(), None);
+    simple_graph.add_edge(node3, node2, "edge2".to_string(), None);
+
+    let options = ExpandOptions::new().ban("banned");
+    let traversed = graph_match::extract_subgraph(&simple_graph, 1, &options, EdgeSelection::Traversed).unwrap();
+    assert_eq!(vec![1, 2], traversed.nodes);
+    assert_eq!(vec![2], traversed.edges);
+
+    let options = ExpandOptions::new().max_depth(1);
+    let induced = graph_match::extract_subgraph(&simple_graph, 1, &options, EdgeSelection::Induced).unwrap();
+    assert_eq!(vec![1, 3, 2], induced.nodes);
+    assert_eq!(vec![1, 2, 3], induced.edges);
+
+    let mut query_graph = graph::Graph::new();
+    let node0 = query_graph.add_node("node0".to_string(), None);
+    let node1 = query_graph.add_node("node1".to_string(), None);
+    let node2 = query_graph.add_node("node2".to_string(), None);
+    query_graph.add_edge(node0, node1, "edge0".to_string(), None);
+    query_graph.add_edge(node1, node2, "edge1".to_string(), None);
+
+    let matches = graph_match::match_graph(&query_graph, 0, &induced.graph, None, &EqualityRequirement::Complete);
+    assert_eq!(1, matches.len());
+    let original: Vec<usize> = matches[0].list.iter().map(|component| induced.original_node(component.node)).collect();
+    assert_eq!(vec![1, 3, 2], original);
+}
+
+#[test]
+fn match_cyclic_query() {
+    let mut simple_graph = graph::Graph::new();
+    let node0 = simple_graph.add_node("node0".to_string(), None);
+    let node1 = simple_graph.add_node("node1".to_string(), None);
+    simple_graph.add_edge(node0, node1, "edge0".to_string(), None);
+    simple_graph.add_edge(node1, node0, "edge1".to_string(), None);
+
+    let mut query_graph = graph::Graph::new();
+    let node0 = query_graph.add_node("node0".to_string(), None);
+    let node1 = query_graph.add_node("node1".to_string(), None);
+    query_graph.add_edge(node0, node1, "edge0".to_string(), None);
+
+    // a cycle in the graph being searched is fine
+    assert_eq!(2, graph_match::match_graph(&query_graph, 0, &simple_graph, None, &EqualityRequirement::Complete).len());
+
+    query_graph.add_edge(node1, node0, "edge1".to_string(), None);
+    assert_eq!(Err(graph_match::Error::Cycle(vec![0, 1, 0])),
+               graph_match::try_match_graph(&query_graph, 0, &simple_graph, None, &EqualityRequirement::Complete));
+}
+
+#[test]
+fn match_streamed_snapshots() {
+    let mut bytes = vec![];
+    for n in 0..3 {
+        let mut sentence_graph = graph::Graph::new();
+        let node0 = sentence_graph.add_node("node0".to_string(), None);
+        let node1 = sentence_graph.add_node("node1".to_string(), None);
+        // the middle graph has no edge for the query to follow
+        if n != 1 {
+            sentence_graph.add_edge(node0, node1, "edge0".to_string(), None);
+        }
+        bytes.extend(snapshot::to_bytes(&sentence_graph));
+    }
+
+    let mut query_graph = graph::Graph::new();
+    let node0 = query_graph.add_node("node0".to_string(), None);
+    let node1 = query_graph.add_node("node1".to_string(), None);
+    query_graph.add_edge(node0, node1, "edge0".to_string(), None);
+
+    let graphs = snapshot::snapshots(&bytes).map(|loaded| loaded.unwrap().to_graph());
+    let mut matches = CorpusMatches::new(&query_graph, 0, graphs, &EqualityRequirement::Complete);
+    assert_eq!(vec![0, 2], matches.by_ref().map(|(graph_id, _)| graph_id).collect::<Vec<_>>());
+    assert_eq!(3, matches.stats().graphs);
+    assert_eq!(vec![1, 0, 1], matches.stats().hits_per_graph);
+}
+
+#[test]
+fn match_with_attribute_index() {
+    let mut simple_graph = graph::Graph::new();
+    for n in 0..6 {
+        let mut attributes = HashMap::new();
+        attributes.insert("lemma".to_string(), if n % 3 == 0 { "dog" } else { "cat" }.to_string());
+        simple_graph.add_node(format!("node{}", n), Some(attributes));
+    }
+    simple_graph.add_edge(0, 1, "edge0".to_string(), None);
+    simple_graph.add_edge(3, 4, "edge0".to_string(), None);
+
+    let mut query_graph = graph::Graph::new();
+    let mut attributes = HashMap::new();
+    attributes.insert("lemma".to_string(), "dog".to_string());
+    let node0 = query_graph.add_node("node0".to_string(), Some(attributes));
+    let node1 = query_graph.add_node("node1".to_string(), None);
+    query_graph.add_edge(node0, node1, "edge0".to_string(), None);
+
+    let unindexed = graph_match::match_graph(&query_graph, 0, &simple_graph, None, &EqualityRequirement::Complete);
+    simple_graph.index_attributes();
+    let indexed = graph_match::match_graph(&query_graph, 0, &simple_graph, None, &EqualityRequirement::Complete);
+    assert_eq!(2, indexed.len());
+    assert_eq!(unindexed, indexed);
+    assert_eq!(unindexed, graph_match::match_graph(&query_graph, 0, &simple_graph, None, &EqualityRequirement::Contains));
+}
+
+#[test]
+fn match_frozen_graph() {
+    let mut simple_graph = graph::Graph::new();
+    for n in 0..4 {
+        let mut attributes = HashMap::new();
+        attributes.insert("key".to_string(), if n == 2 { "other" } else { "value" }.to_string());
+        simple_graph.add_node(format!("node{}", n), Some(attributes));
+    }
+    for &(source, target) in &[(0, 1), (1, 2), (1, 3)] {
+        let mut attributes = HashMap::new();
+        attributes.insert("to".to_string(), format!("node{}", target));
+        simple_graph.add_edge(source, target, "edge".to_string(), Some(attributes));
+    }
+
+    let mut query_graph = graph::Graph::new();
+    let mut attributes = HashMap::new();
+    attributes.insert("key".to_string(), "other".to_string());
+    let node0 = query_graph.add_node("node0".to_string(), None);
+    let node1 = query_graph.add_node("node1".to_string(), Some(attributes));
+    let mut attributes = HashMap::new();
+    attributes.insert("to".to_string(), "node2".to_string());
+    query_graph.add_edge(node0, node1, "edge".to_string(), Some(attributes));
+
+    let expected = graph_match::match_graph(&query_graph, 0, &simple_graph, None, &EqualityRequirement::Complete);
+    assert_eq!(1, expected.len());
+    let frozen_graph = simple_graph.freeze();
+    assert_eq!(expected, graph_match::match_graph(&query_graph, 0, &frozen_graph, None, &EqualityRequirement::Complete));
+    assert_eq!(vec![0, 1, 2, 3], Bfs::new(&frozen_graph, 0).collect::<Vec<_>>());
+
+    let simple_graph = frozen_graph.thaw();
+    assert_eq!(vec![0, 1, 2, 3], Bfs::new(&simple_graph, 0).collect::<Vec<_>>());
+}
+
+#[test]
+fn match_interned_graph() {
+    let mut simple_graph: graph::Graph<Option<InternedAttributes>, Option<InternedAttributes>> = graph::Graph::default();
+    simple_graph.intern_identifiers();
+    for n in 0..4 {
+        let mut attributes = HashMap::new();
+        attributes.insert("lemma".to_string(), if n % 2 == 0 { "dog" } else { "cat" }.to_string());
+        let interned = simple_graph.intern_attributes(&attributes);
+        simple_graph.add_node(format!("node{}", n), Some(interned));
+    }
+    simple_graph.add_edge(0, 1, "edge0", None);
+    simple_graph.add_edge(2, 3, "edge0", None);
+    simple_graph.index_attributes();
+
+    let mut query_graph = graph::Graph::new();
+    let mut attributes = HashMap::new();
+    attributes.insert("lemma".to_string(), "dog".to_string());
+    let node0 = query_graph.add_node("node0", Some(attributes));
+    let node1 = query_graph.add_node("node1", None);
+    query_graph.add_edge(node0, node1, "edge0", None);
+
+    let matches = graph_match::match_graph(&query_graph, 0, &simple_graph, None, &EqualityRequirement::Complete);
+    assert_eq!(2, matches.len());
+    assert_eq!(vec![0, 1], matches[0].list.iter().map(|component| component.node).collect::<Vec<_>>());
+    assert_eq!(vec![0, 1], simple_graph.edges_by_identifier("edge0"));
+}
+
+#[test]
+fn diff_rewritten_graph() {
+    let mut simple_graph = graph::Graph::new();
+    for n in 0..3 {
+        simple_graph.add_node(format!("node{}", n), None);
+    }
+    simple_graph.add_edge(0, 1, "edge0", None);
+    simple_graph.add_edge(1, 2, "edge1", None);
+    let bytes = snapshot::to_bytes(&simple_graph);
+    assert_eq!(simple_graph, snapshot::read(&mut &bytes[..]).unwrap());
+
+    let mut pattern = graph::Graph::new();
+    pattern.add_node("node0", None);
+    pattern.add_node("node1", None);
+    pattern.add_edge(0, 1, "edge", None);
+    let rule = Rule::new(pattern, 0).relabel_edge(EdgeRef::Matched(0), Value::Text("edge".to_string()));
+    let mut rewritten = snapshot::read(&mut &bytes[..]).unwrap();
+    assert_eq!(2, rewrite::apply_to_fixpoint(&[rule], &mut rewritten, 5));
+
+    let changes = diff::diff(&simple_graph, &rewritten, NodeMatching::ByIdentifier);
+    assert!(changes.changed_nodes.is_empty());
+    assert_eq!(vec![0, 1], changes.removed_edges);
+    assert_eq!(vec![0, 1], changes.added_edges);
+    let changes = diff::diff(&simple_graph, &rewritten, NodeMatching::ByIndex);
+    assert_eq!(2, changes.changed_edges.len());
+    assert_eq!(Some(("edge1".into(), "edge".into())), changes.changed_edges[1].identifier);
+}
+
+#[test]
+fn deduplicate_extracted_patterns() {
+    // two clauses with the same shape, extracted as separate subgraphs
+    let mut simple_graph = graph::Graph::new();
+    for &(verb, object) in &[("see", "dog"), ("see", "dog")] {
+        let mut attributes = HashMap::new();
+        attributes.insert("word".to_string(), verb.to_string());
+        let head = simple_graph.add_node("token", Some(attributes));
+        let mut attributes = HashMap::new();
+        attributes.insert("word".to_string(), object.to_string());
+        let object = simple_graph.add_node("token", Some(attributes));
+        simple_graph.add_edge(head, object, "dobj", None);
+    }
+    let options = ExpandOptions::new();
+    let first = graph_match::extract_subgraph(&simple_graph, 0, &options, EdgeSelection::Traversed).unwrap();
+    let second = graph_match::extract_subgraph(&simple_graph, 2, &options, EdgeSelection::Traversed).unwrap();
+    assert!(canonical::is_isomorphic(&first.graph, &second.graph));
+    assert_eq!(canonical::canonical_hash(&first.graph), canonical::canonical_hash(&second.graph));
+    assert!(!canonical::is_isomorphic(&first.graph, &simple_graph));
+}